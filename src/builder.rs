@@ -0,0 +1,70 @@
+use dfa::Dfa;
+use std::collections::HashMap;
+
+/// Index of a state in the DFA being built
+pub type StateId = usize;
+
+/// Build a `Dfa<char>` naming states instead of juggling indexes, e.g.
+/// `DfaBuilder::new().accept("S").edge("S", 'a', "A").edge("A", 'b', "S").build()` for `(ab)*`.
+/// States are created the first time their name is used. The first one is the initial state,
+/// unless `initial` says otherwise
+pub struct DfaBuilder {
+    dfa: Dfa<char>,
+    names: HashMap<String, StateId>
+}
+
+#[allow(dead_code)]
+impl DfaBuilder {
+    pub fn new() -> Self {
+        DfaBuilder { dfa: Dfa::new(), names: HashMap::new() }
+    }
+
+    /// Index of the state `name`, created if needed
+    pub fn state(&mut self, name: &str) -> StateId {
+        if let Some(index) = self.names.get(name) {
+            return *index;
+        }
+
+        // The DFA starts with its initial state, the first name takes it
+        let index = if self.names.is_empty() { *self.dfa.initial() } else { self.dfa.add_state(false) };
+
+        self.names.insert(name.to_string(), index);
+
+        index
+    }
+
+    /// Make `name` the initial state
+    pub fn initial(&mut self, name: &str) -> &mut Self {
+        let index = self.state(name);
+
+        self.dfa.set_initial(index);
+        self.dfa.rewind();
+
+        self
+    }
+
+    /// Make `name` an accepting state
+    pub fn accept(&mut self, name: &str) -> &mut Self {
+        let index = self.state(name);
+
+        self.dfa.set_current(index).expect("Named states exist");
+        self.dfa.set_current_state_accept(true);
+        self.dfa.rewind();
+
+        self
+    }
+
+    /// Add a transition from `from` to `to` by `symbol`
+    pub fn edge(&mut self, from: &str, symbol: char, to: &str) -> &mut Self {
+        let (from, to) = (self.state(from), self.state(to));
+
+        self.dfa.create_transition_between(&from, &to, symbol);
+
+        self
+    }
+
+    /// The DFA built so far, the builder can still be used after
+    pub fn build(&self) -> Dfa<char> {
+        self.dfa.clone()
+    }
+}
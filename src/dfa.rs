@@ -1,26 +1,56 @@
-use std::collections::{ HashSet, HashMap, VecDeque };
-use std::hash::Hash;
-use std::fmt::{ Display, Debug };
+use std::collections::{ BTreeMap, BTreeSet, HashSet, HashMap, VecDeque };
+use std::hash::{ Hash, Hasher };
+use std::fmt::{ self, Display, Debug };
+use std::error::Error;
 use std::mem;
-
-pub trait Transitable: PartialEq + Eq + Hash + Clone {}
+use std::cmp::{ Ordering, Reverse };
+use std::rc::Rc;
+use json::{ self, JsonError, Value };
+use lexer::{ LexError, Token };
+#[cfg(feature = "serde")]
+use serde::{ Serialize, Deserialize };
+#[cfg(feature = "rand")]
+use rand::Rng;
+
+pub trait Transitable: PartialEq + Eq + Hash + Clone + Ord {}
 impl Transitable for char {}
+impl Transitable for u8 {}
+impl Transitable for String {}
+impl Transitable for usize {}
+impl Transitable for Symbol {}
 
 /// State = true => State Accept
 pub type State = bool;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Transition<T>(T, usize);
 
 impl<T: Transitable> Transition<T> {
     pub fn new(by: T, dest: usize) -> Self {
         Transition(by, dest)
     }
+
+    /// Symbol consumed by the transition
+    pub fn by(&self) -> &T {
+        &self.0
+    }
+
+    /// Index of the destination state
+    pub fn dest(&self) -> usize {
+        self.1
+    }
 }
 
 #[allow(dead_code)]
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "T: Serialize",
+    deserialize = "T: Deserialize<'de> + Ord"
+)))]
 pub struct Dfa<T> {
-    states: HashMap<usize, State>,
+    states: BTreeMap<usize, State>,
 
     /// Index on `states` which is the initial state
     initial: usize,
@@ -28,8 +58,233 @@ pub struct Dfa<T> {
     /// The current state DFA is into
     current: usize,
 
-    transitions: HashMap<usize, HashSet<Transition<T>>>,
-    alphabet: HashSet<T>
+    transitions: BTreeMap<usize, BTreeSet<Transition<T>>>,
+
+    /// Transitions that consume no symbol, e.g. `<A> ::= <B>`
+    epsilon: BTreeMap<usize, BTreeSet<usize>>,
+
+    alphabet: BTreeSet<T>,
+
+    /// Token names of states, e.g. `IDENT` for the accepting states of `<IDENT>`
+    #[cfg_attr(feature = "serde", serde(default))]
+    labels: HashMap<usize, String>,
+
+    /// Which label wins when states are merged, the highest one. States not here have 0
+    #[cfg_attr(feature = "serde", serde(default))]
+    priorities: HashMap<usize, i32>,
+
+    /// Whether the exporters leave out the trap states, see `hide_trap_in_output`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hide_trap: bool
+}
+
+/// Size and shape of a DFA, see `Dfa::stats`
+#[allow(dead_code)]
+#[derive(Debug, PartialEq, Clone)]
+pub struct DfaStats {
+    pub num_states: usize,
+    /// Transitions by a symbol, epsilon transitions are counted apart
+    pub num_transitions: usize,
+    pub num_epsilon_transitions: usize,
+    pub num_accepting: usize,
+    pub alphabet_size: usize,
+    pub deterministic: bool,
+    pub complete: bool
+}
+
+/// Names of interned symbols, each name gets the next id. See `Dfa::<String>::intern_symbols`
+#[allow(dead_code)]
+#[derive(Debug, Default, Clone)]
+pub struct SymbolTable {
+    ids: HashMap<String, usize>,
+    names: Vec<String>
+}
+
+#[allow(dead_code)]
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Id of `name`, interning it if it is new
+    pub fn intern(&mut self, name: &str) -> usize {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), self.names.len() - 1);
+
+        self.names.len() - 1
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).cloned()
+    }
+
+    pub fn name(&self, id: usize) -> Option<&str> {
+        self.names.get(id).map(|n| n.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Copy of `dfa` with the names back as the symbols. Panics on a symbol of another table
+    /// with an id this one never gave
+    pub fn resolve(&self, dfa: &Dfa<Symbol>) -> Dfa<String> {
+        dfa.map_symbols(|symbol| self.names[symbol.id].clone())
+    }
+}
+
+/// A symbol interned in a `SymbolTable`. It is compared, ordered and hashed by its id, so
+/// only symbols of the same table should be mixed, and shown by its name, which is what
+/// `to_csv`, `to_dot` and friends print
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct Symbol {
+    id: usize,
+    table: Rc<SymbolTable>
+}
+
+#[allow(dead_code)]
+impl Symbol {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.table.names[self.id]
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Symbol {}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.name())
+    }
+}
+
+/// A position in a run of a DFA, fed one symbol at a time, see `Dfa::cursor`. Unlike `walk`
+/// it only borrows the DFA, so many can scan it at once
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct DfaCursor<'a, T: 'a> {
+    dfa: &'a Dfa<T>,
+    /// `None` once a symbol had no transition, the run can only reject from there
+    state: Option<usize>
+}
+
+#[allow(dead_code)]
+impl<'a, T: Transitable + Debug> DfaCursor<'a, T> {
+    /// Follow the transition by `sym`, returns whether there was one. Without it the cursor
+    /// is stuck until `reset`
+    pub fn feed(&mut self, sym: &T) -> bool {
+        self.state = self.state.and_then(|s| self.dfa.transition(s, sym));
+
+        self.state.is_some()
+    }
+
+    /// Whether the symbols fed so far are accepted
+    pub fn is_accepting(&self) -> bool {
+        self.state.is_some_and(|s| self.dfa.state_accept(s))
+    }
+
+    /// Current state, `None` if a symbol had no transition
+    pub fn state(&self) -> Option<usize> {
+        self.state
+    }
+
+    /// Back to the initial state, as if nothing was fed
+    pub fn reset(&mut self) {
+        self.state = Some(self.dfa.initial);
+    }
+}
+
+/// Structural equality: same states, indexes and transitions. The current state and
+/// `hide_trap_in_output` are ignored.
+/// This is not language equivalence, two DFAs may accept the same inputs and still differ here
+impl<T: Hash + Eq> PartialEq for Dfa<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.initial == other.initial
+            && self.states == other.states
+            && self.transitions == other.transitions
+            && self.epsilon == other.epsilon
+            && self.alphabet == other.alphabet
+            && self.labels == other.labels
+            && self.priorities == other.priorities
+    }
+}
+
+impl<T: Hash + Eq> Eq for Dfa<T> {}
+
+/// Hashes the `canonical_form`, so DFAs of the same language hash equal even when they are
+/// not `==`. Each hash minimizes a copy of the DFA, which is not cheap: keep the hash (or the
+/// canonical form) around instead of rehashing large automata
+impl<T: Transitable + Debug> Hash for Dfa<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let canonical = self.canonical_form();
+
+        canonical.initial.hash(state);
+        canonical.states.hash(state);
+        canonical.transitions.hash(state);
+        canonical.alphabet.hash(state);
+        // Sorted, hash maps iterate in any order
+        canonical.labels.iter().collect::<BTreeMap<_, _>>().hash(state);
+        canonical.priorities.iter().collect::<BTreeMap<_, _>>().hash(state);
+    }
+}
+
+/// A summary with counts rather than every transition, `Display` prints the whole table
+impl<T: Debug> Debug for Dfa<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let accepting: Vec<&usize> = self.states.iter().filter(|s| *s.1).map(|s| s.0).collect();
+
+        f.debug_struct("Dfa")
+            .field("initial", &self.initial)
+            .field("current", &self.current)
+            .field("accepting", &accepting)
+            .field("states", &self.states.len())
+            .field("transitions", &self.transitions.values().map(|ts| ts.len()).sum::<usize>())
+            .field("epsilon", &self.epsilon.values().map(|es| es.len()).sum::<usize>())
+            .field("alphabet", &self.alphabet)
+            .finish()
+    }
 }
 
 impl<T: Hash + Eq> Dfa<T> {
@@ -38,20 +293,24 @@ impl<T: Hash + Eq> Dfa<T> {
         Self {
             // Initial state is already created
             states: {
-                let mut hm = HashMap::new();
+                let mut hm = BTreeMap::new();
                 hm.insert(0, false);
 
                 hm
             },
-            alphabet: HashSet::new(),
+            alphabet: BTreeSet::new(),
             initial: 0,
             current: 0,
-            transitions: HashMap::new()
+            transitions: BTreeMap::new(),
+            epsilon: BTreeMap::new(),
+            labels: HashMap::new(),
+            priorities: HashMap::new(),
+            hide_trap: false
         }
     }
 
     #[allow(dead_code)]
-    pub fn states(&self) -> &HashMap<usize, State> {
+    pub fn states(&self) -> &BTreeMap<usize, State> {
         &self.states
     }
 
@@ -81,6 +340,12 @@ impl<T: Hash + Eq> Dfa<T> {
         self.current = self.initial;
     }
 
+    /// Same as `rewind`, back to the initial state
+    #[allow(dead_code)]
+    pub fn reset(&mut self) {
+        self.rewind()
+    }
+
     #[allow(dead_code)]
     pub fn current(&self) -> usize {
         self.current
@@ -94,8 +359,26 @@ impl<T: Hash + Eq> Dfa<T> {
         }
     }
 
+    /// Same as `state_accept`
+    #[allow(dead_code)]
+    pub fn is_accepting(&self, state: usize) -> bool {
+        self.state_accept(state)
+    }
+
+    /// Indexes of the accepting states, sorted
+    pub fn accepting_states(&self) -> Vec<usize> {
+        self.states.iter().filter(|&(_, a)| *a).map(|(s, _)| *s).collect()
+    }
+
+    /// Indexes of the non-accepting states, sorted
+    #[allow(dead_code)]
+    pub fn non_accepting_states(&self) -> Vec<usize> {
+        self.states.iter().filter(|&(_, a)| !*a).map(|(s, _)| *s).collect()
+    }
+
     pub fn set_current(&mut self, t: usize) -> Result<(), &str> {
-        if t <= self.states.len() {
+        // States may be sparse after removals, so check the index itself
+        if self.states.contains_key(&t) {
             self.current = t;
             Ok(())
         } else {
@@ -104,21 +387,199 @@ impl<T: Hash + Eq> Dfa<T> {
     }
 
     #[allow(dead_code)]
-    pub fn alphabet(&self) -> &HashSet<T> {
+    pub fn alphabet(&self) -> &BTreeSet<T> {
         &self.alphabet
     }
 
     #[allow(dead_code)]
-    pub fn transitions(&self) -> &HashMap<usize, HashSet<Transition<T>>> {
+    pub fn transitions(&self) -> &BTreeMap<usize, BTreeSet<Transition<T>>> {
         &self.transitions
     }
 
+    #[allow(dead_code)]
+    pub fn num_states(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Transitions by a symbol, each destination of a nondeterministic one counted apart.
+    /// Epsilon transitions are not counted
+    pub fn num_transitions(&self) -> usize {
+        self.transitions.values().map(|ts| ts.len()).sum()
+    }
+
+    /// Symbols of the alphabet, in their order
+    #[allow(dead_code)]
+    pub fn alphabet_sorted(&self) -> Vec<&T> {
+        self.alphabet.iter().collect()
+    }
+
+    /// Every transition as `(from, symbol, to)`, sorted by origin, symbol and destination
+    #[allow(dead_code)]
+    pub fn iter_transitions<'a>(&'a self) -> impl Iterator<Item = (usize, &'a T, usize)> + 'a {
+        self.transitions
+            .iter()
+            .flat_map(|(from, ts)| ts.iter().map(move |t| (*from, &t.0, t.1)))
+    }
+
+    /// Transitions leaving `state` as `(symbol, to)`, sorted
+    #[allow(dead_code)]
+    pub fn transitions_from<'a>(&'a self, state: usize) -> impl Iterator<Item = (&'a T, usize)> + 'a {
+        self.transitions
+            .get(&state)
+            .into_iter()
+            .flat_map(|ts| ts.iter().map(|t| (&t.0, t.1)))
+    }
+
+    /// Transitions arriving at `state` as `(from, symbol)`, sorted
+    #[allow(dead_code)]
+    pub fn transitions_to<'a>(&'a self, state: usize) -> impl Iterator<Item = (usize, &'a T)> + 'a {
+        self.iter_transitions()
+            .filter(move |t| t.2 == state)
+            .map(|(from, by, _)| (from, by))
+    }
+
     pub fn set_current_state_accept(&mut self, accept: bool) {
         self.states.insert(self.current, accept);
     }
+
+    /// Name the token recognized by `state`
+    pub fn set_state_label(&mut self, state: usize, name: &str) {
+        self.labels.insert(state, name.to_string());
+    }
+
+    /// Token name of `state`, if it has one
+    #[allow(dead_code)]
+    pub fn state_label(&self, state: usize) -> Option<&str> {
+        self.labels.get(&state).map(|l| l.as_str())
+    }
+
+    /// Set the priority of `state` label, e.g. a keyword over the identifier rule
+    #[allow(dead_code)]
+    pub fn set_priority(&mut self, state: usize, priority: i32) {
+        self.priorities.insert(state, priority);
+    }
+
+    /// Priority of `state` label, 0 unless set
+    pub fn priority(&self, state: usize) -> i32 {
+        self.priorities.get(&state).cloned().unwrap_or(0)
+    }
+
+    /// State whose label a merge of `states` takes: the accepting one with a label and the
+    /// highest priority. On ties the lowest index wins, so the token defined first
+    fn label_winner<'a, I: IntoIterator<Item = &'a usize>>(&self, states: I) -> Option<usize> {
+        states.into_iter()
+            .filter(|s| self.state_accept(**s) && self.labels.contains_key(s))
+            .max_by_key(|s| (self.priority(**s), Reverse(**s)))
+            .cloned()
+    }
+
+    /// Give `state` the label and priority of `winner`
+    fn inherit_label(&mut self, state: usize, winner: usize) {
+        let label = self.labels[&winner].clone();
+
+        self.labels.insert(state, label);
+
+        match self.priorities.get(&winner).cloned() {
+            Some(priority) => self.priorities.insert(state, priority),
+            None => self.priorities.remove(&state)
+        };
+    }
+
+    #[allow(dead_code)]
+    pub fn epsilon_transitions(&self) -> &BTreeMap<usize, BTreeSet<usize>> {
+        &self.epsilon
+    }
+
+    /// Create an epsilon transition between states `origin` and `dest`
+    pub fn create_epsilon_transition_between(&mut self, origin: &usize, dest: &usize) {
+        self.epsilon.entry(*origin).or_default().insert(*dest);
+    }
+
+    /// Create an epsilon transition between the current state and `dest`
+    pub fn create_epsilon_transition(&mut self, dest: usize) {
+        let current = self.current;
+        self.create_epsilon_transition_between(&current, &dest)
+    }
+
+    /// All states reachable from `states` using only epsilon transitions, `states` included
+    pub fn epsilon_closure(&self, states: &BTreeSet<usize>) -> BTreeSet<usize> {
+        let mut closure = states.clone();
+        let mut next: Vec<usize> = states.iter().cloned().collect();
+
+        while let Some(s) = next.pop() {
+            if let Some(es) = self.epsilon.get(&s) {
+                for e in es {
+                    if closure.insert(*e) {
+                        next.push(*e);
+                    }
+                }
+            }
+        }
+
+        closure
+    }
+
+    /// Non-accepting states whose transitions, if any, all lead back to themselves, sorted.
+    /// Unlike dead states in general, an input never leaves one once it gets there, like the
+    /// state added by `insert_error_state`
+    #[allow(dead_code)]
+    pub fn trap_states(&self) -> Vec<usize> {
+        self.states
+            .keys()
+            .filter(|s| !self.state_accept(**s))
+            .filter(|s| self.transitions_from(**s).all(|(_, to)| to == **s))
+            .filter(|s| self.epsilon.get(s).into_iter().flatten().all(|to| to == *s))
+            .cloned()
+            .collect()
+    }
+
+    /// Leave the trap states and the transitions into them out of `to_csv`, `to_dot` and
+    /// `to_mermaid`, so a complete DFA prints like a partial one. The automaton itself keeps
+    /// them, nothing changes for `accepts` or `complement`. A trap initial state is still shown
+    #[allow(dead_code)]
+    pub fn hide_trap_in_output(&mut self, hide: bool) {
+        self.hide_trap = hide;
+    }
+
+    /// States the exporters leave out, see `hide_trap_in_output`
+    fn hidden_states(&self) -> BTreeSet<usize> {
+        if !self.hide_trap {
+            return BTreeSet::new();
+        }
+
+        self.trap_states().into_iter().filter(|s| *s != self.initial).collect()
+    }
 }
 
 impl<T: Transitable + Debug> Dfa<T> {
+    /// Build an automaton from its `edges`, every state they, `initial` or `accepting` mention
+    /// is created
+    #[allow(dead_code)]
+    pub fn from_transitions(initial: usize, accepting: &[usize], edges: &[(usize, T, usize)]) -> Dfa<T> {
+        let mut dfa = Dfa::new();
+
+        dfa.states.clear();
+        dfa.states.insert(initial, false);
+
+        for &(from, _, to) in edges {
+            dfa.states.insert(from, false);
+            dfa.states.insert(to, false);
+        }
+
+        for s in accepting {
+            dfa.states.insert(*s, true);
+        }
+
+        dfa.initial = initial;
+        dfa.current = initial;
+
+        for &(from, ref by, to) in edges {
+            dfa.create_transition_between(&from, &to, by.clone());
+        }
+
+        dfa
+    }
+
     /// Add a existing `Transition` to `state`
     pub fn add_transition_to(&mut self, state: &usize, trans: Transition<T>) {
         self.alphabet.insert(trans.0.clone());
@@ -126,7 +587,7 @@ impl<T: Transitable + Debug> Dfa<T> {
         if self.transitions.contains_key(state) {
             self.transitions.get_mut(state).unwrap().insert(trans);
         } else {
-            let mut set = HashSet::new();
+            let mut set = BTreeSet::new();
             set.insert(trans);
             self.transitions.insert(*state, set);
         }
@@ -153,14 +614,212 @@ impl<T: Transitable + Debug> Dfa<T> {
         self.current = dest;
     }
 
+    /// Remove the transition from `from` to `to` by `by`, returns whether it existed. A symbol
+    /// no transition uses anymore leaves the alphabet
+    #[allow(dead_code)]
+    pub fn remove_transition(&mut self, from: usize, by: &T, to: usize) -> bool {
+        let removed = match self.transitions.get_mut(&from) {
+            Some(ts) => ts.remove(&Transition(by.clone(), to)),
+            None     => false
+        };
+
+        if self.transitions.get(&from).is_some_and(|ts| ts.is_empty()) {
+            self.transitions.remove(&from);
+        }
+
+        if removed && !self.transitions.values().flatten().any(|t| &t.0 == by) {
+            self.alphabet.remove(by);
+        }
+
+        removed
+    }
+
+    /// Recompute the alphabet from the symbols the transitions use, dropping those left without
+    /// any transition
+    pub fn prune_alphabet(&mut self) {
+        self.alphabet = self.transitions.values().flatten().map(|t| t.0.clone()).collect();
+    }
+
+    /// Remove every transition by `symbol` and `symbol` from the alphabet. States only reached
+    /// through it are left behind, see `remove_unreachable_states`
+    #[allow(dead_code)]
+    pub fn project(&mut self, symbol: &T) {
+        for ts in self.transitions.values_mut() {
+            ts.retain(|t| &t.0 != symbol);
+        }
+
+        self.transitions.retain(|_, ts| !ts.is_empty());
+        self.alphabet.remove(symbol);
+    }
+
+    /// Run `input` from the initial state following its transitions, returns whether the last
+    /// state accepts. A symbol without transition rejects the whole input
+    #[allow(dead_code)]
+    pub fn accepts(&self, input: &[T]) -> bool {
+        let mut current = self.initial;
+
+        for c in input {
+            match self.transition(current, c) {
+                Some(next) => current = next,
+                None       => return false
+            }
+        }
+
+        self.state_accept(current)
+    }
+
+    /// A cursor at the initial state, to feed input as it comes. The DFA's own current state
+    /// is left alone
+    #[allow(dead_code)]
+    pub fn cursor(&self) -> DfaCursor<'_, T> {
+        DfaCursor { dfa: self, state: Some(self.initial) }
+    }
+
+    /// Length of the longest prefix of `input` the DFA accepts, `Some(0)` if only the empty one
+    /// is, or `None` if none is. The run stops at the first symbol without transition
+    #[allow(dead_code)]
+    pub fn longest_prefix_match(&self, input: &[T]) -> Option<usize> {
+        let mut current = self.initial;
+        let mut longest = if self.state_accept(current) { Some(0) } else { None };
+
+        for (i, c) in input.iter().enumerate() {
+            match self.transition(current, c) {
+                Some(next) => current = next,
+                None       => break
+            }
+
+            if self.state_accept(current) {
+                longest = Some(i + 1);
+            }
+        }
+
+        longest
+    }
+
+    /// States visited running `input`, the initial one included, and whether the run ended
+    /// accepting. A symbol without transition stops the trace there and rejects
+    #[allow(dead_code)]
+    pub fn run_trace(&self, input: &[T]) -> (Vec<usize>, bool) {
+        let mut trace = vec![self.initial];
+
+        for c in input {
+            match self.transition(trace[trace.len() - 1], c) {
+                Some(next) => trace.push(next),
+                None       => return (trace, false)
+            }
+        }
+
+        let accept = self.state_accept(trace[trace.len() - 1]);
+
+        (trace, accept)
+    }
+
+    /// Destination of the transition from `state` by `by`, if any. Transitions are sorted by
+    /// symbol, so this is a logarithmic search. A nondeterministic state gives its lowest
+    /// destination
+    pub fn transition(&self, state: usize, by: &T) -> Option<usize> {
+        let ts = self.transitions.get(&state)?;
+        let mut matching = ts.range(Transition(by.clone(), 0)..).take_while(|t| &t.0 == by);
+        let dest = matching.next().map(|t| t.1);
+
+        if matching.next().is_some() {
+            debug!("Nondeterministic transition from {} by {:?}, taking {:?}", state, by, dest);
+        }
+
+        dest
+    }
+
+    /// Follow the transition from the current state by `by`, returning the new current state.
+    /// Nothing is created, so a missing transition is an error
+    #[allow(dead_code)]
+    pub fn walk(&mut self, by: &T) -> Result<usize, &'static str> {
+        let current = self.current;
+
+        match self.transition(current, by) {
+            Some(dest) => {
+                self.current = dest;
+                Ok(dest)
+            },
+            None => Err("No transition from current state")
+        }
+    }
+
+    /// Give the state `old` the index `new`, moving its transitions, label and priority and
+    /// every transition into it. The initial and current states follow. Fails if `old` does
+    /// not exist or `new` already does
+    #[allow(dead_code)]
+    pub fn rename_state(&mut self, old: usize, new: usize) -> Result<(), &'static str> {
+        if !self.states.contains_key(&old) {
+            return Err("Non existant state");
+        }
+
+        if old == new {
+            return Ok(());
+        }
+
+        if self.states.contains_key(&new) {
+            return Err("State already exists");
+        }
+
+        let accept = self.states.remove(&old).unwrap();
+        self.states.insert(new, accept);
+
+        if let Some(ts) = self.transitions.remove(&old) {
+            self.transitions.insert(new, ts);
+        }
+
+        if let Some(es) = self.epsilon.remove(&old) {
+            self.epsilon.insert(new, es);
+        }
+
+        if let Some(label) = self.labels.remove(&old) {
+            self.labels.insert(new, label);
+        }
+
+        if let Some(priority) = self.priorities.remove(&old) {
+            self.priorities.insert(new, priority);
+        }
+
+        for ts in self.transitions.values_mut() {
+            let into: Vec<Transition<T>> = ts.iter().filter(|t| t.1 == old).cloned().collect();
+
+            for t in into {
+                ts.remove(&t);
+                ts.insert(Transition(t.0, new));
+            }
+        }
+
+        for es in self.epsilon.values_mut() {
+            if es.remove(&old) {
+                es.insert(new);
+            }
+        }
+
+        if self.initial == old { self.initial = new; }
+        if self.current == old { self.current = new; }
+
+        Ok(())
+    }
+
     /// Removes a state from DFA, returns an Option with informations if state was accepting and
-    /// its transitions
-    pub fn remove_state(&mut self, index: usize) -> Option<(bool, Option<HashSet<Transition<T>>>)> {
+    /// its transitions. Removing the current state rewinds to the initial one
+    pub fn remove_state(&mut self, index: usize) -> Option<(bool, Option<BTreeSet<Transition<T>>>)> {
+        if self.current == index {
+            self.rewind();
+        }
+
         for ts in self.transitions.values_mut() {
             ts.retain(|x| x.1 != index);
         }
 
+        for es in self.epsilon.values_mut() {
+            es.remove(&index);
+        }
+
         if self.states.contains_key(&index) {
+            self.epsilon.remove(&index);
+            self.labels.remove(&index);
+            self.priorities.remove(&index);
             Some((self.states.remove(&index).unwrap(), self.transitions.remove(&index)))
         } else {
             None
@@ -173,13 +832,19 @@ impl<T: Transitable + Debug> Dfa<T> {
     ///     char2: {dest4, dest1, dest3},
     ///     char3: {dest4, dest2}
     /// }
-    pub fn ndt_of(&self, index: &usize) -> HashMap<T, HashSet<usize>> {
-        let mut ndt = HashMap::new();
+    /// A state without transitions, or not in the DFA at all, gives an empty map
+    pub fn ndt_of(&self, index: &usize) -> BTreeMap<T, BTreeSet<usize>> {
+        let mut ndt = BTreeMap::new();
+
+        let transitions = match self.transitions.get(index) {
+            Some(ts) => ts,
+            None     => return ndt
+        };
 
         for c in &self.alphabet {
-            let mut multiple = HashSet::new();
+            let mut multiple = BTreeSet::new();
 
-            for t in &self.transitions[index] {
+            for t in transitions {
                 if &t.0 == c {
                     multiple.insert(t.1);
                 }
@@ -205,8 +870,8 @@ impl<T: Transitable + Debug> Dfa<T> {
     ///     },
     ///     state_indexX: ndt_of(state_indexX)
     /// }
-    pub fn non_determinist_states(&self) -> Option<HashMap<usize, HashMap<T, HashSet<usize>>>> {
-        let mut ndet = HashMap::new();
+    pub fn non_determinist_states(&self) -> Option<BTreeMap<usize, BTreeMap<T, BTreeSet<usize>>>> {
+        let mut ndet = BTreeMap::new();
 
         for s in self.transitions.keys() {
             let ndt = self.ndt_of(s);
@@ -223,215 +888,188 @@ impl<T: Transitable + Debug> Dfa<T> {
         }
     }
 
-    /// Remove non-deterministic states from the DFA
-    pub fn determinize(&mut self) {
-        let mut state_map: HashMap<usize, HashSet<usize>> = HashMap::new();
-
-        while let Some(non_deterministic) = self.non_determinist_states() {
-            // Map the new created states and their new transitions
-            let mut new_states: HashMap<usize, Vec<_>> = HashMap::new();
-
-            // {usize => {T => usize [dest]}}
-            for (s, by) in non_deterministic {
-                // {T => usize}
-                // First, for each non-deterministic transition, map a new state
-                for (c, to) in &by {
-                    let mut trans_to: HashSet<_> = HashSet::new();
-                    let mut has_equivalent: Option<usize> = None;
-                    let mut ndtrans = Vec::new(); // Vec of non-det transitions
-
-                    // Parse all transitions of the future new determinized state
-                    for t in to {
-                        // If target states are created by minimization, then get its
-                        // original (the ones whose created the first state) transitions,
-                        // else simply insert the state
-                        if state_map.contains_key(t) {
-                            trans_to = trans_to.union(&state_map[t]).cloned().collect();
-                        } else {
-                            trans_to.insert(t.to_owned());
-                        }
-                    }
-
-                    // Check if there is any equivalent determinized transition created
-                    for (ns, mapped) in &state_map {
-                        if mapped == &trans_to {
-                            has_equivalent = Some(ns.to_owned());
-                            break;
-                        }
-                    }
+    /// `non_determinist_states` flattened into `(state, symbol, destinations)`, sorted by state
+    /// and then symbol
+    pub fn nondeterminism_report(&self) -> Vec<(usize, T, Vec<usize>)> {
+        self.non_determinist_states()
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|(s, ndt)| {
+                ndt.into_iter().map(move |(by, dests)| (s, by, dests.into_iter().collect()))
+            })
+            .collect()
+    }
 
-                    // If some of mapped transitions are equivalent, then use this state as target
-                    // to the non-deterministic transition, else create and map the new transition
-                    let newstate = if let Some(st) = has_equivalent { st } else {
-                        let mut accept = false;
+    /// Whether there are no epsilon transitions nor states with multiple transitions by the
+    /// same symbol
+    pub fn is_deterministic(&self) -> bool {
+        self.epsilon.is_empty() && self.non_determinist_states().is_none()
+    }
 
-                        // Check if any target states from transitions accept
-                        for target in to.iter() {
-                            if self.state_accept(target.to_owned()) {
-                                accept = true;
-                                break;
-                            }
-                        }
+    /// Remove non-deterministic states and epsilon transitions from the DFA, returns whether
+    /// anything had to be changed. Subset construction: starting from the epsilon closure of
+    /// the initial state, each set of states reached gets a state whose transitions go to the
+    /// closures of where its members go. The initial state and sets of a single state keep
+    /// their index, other sets get new ones, and states no set reaches are gone. The current
+    /// state is kept if its index survives, else the DFA is rewound
+    pub fn determinize(&mut self) -> bool {
+        if self.is_deterministic() { return false; }
+
+        let mut fresh = self.states.keys().next_back().map_or(0, |s| s + 1);
+        let mut indexes: HashMap<BTreeSet<usize>, usize> = HashMap::new();
+        let mut transitions: BTreeMap<usize, BTreeSet<Transition<T>>> = BTreeMap::new();
+        let mut next = VecDeque::new();
+        let initial = self.initial;
+        let start = self.epsilon_closure(&[initial].iter().cloned().collect());
 
-                        let index = self.add_state(accept);
+        // The initial state keeps its index whatever its closure is
+        indexes.insert(start.clone(), initial);
+        next.push_back(start);
 
-                        state_map.insert(index, trans_to);
+        {
+            let mut index_of = |subset: BTreeSet<usize>, next: &mut VecDeque<BTreeSet<usize>>| -> usize {
+                if let Some(index) = indexes.get(&subset) {
+                    return *index;
+                }
 
-                        index
-                    };
+                let index = if subset.len() == 1 && !subset.contains(&initial) {
+                    *subset.iter().next().unwrap()
+                } else {
+                    fresh += 1;
+                    fresh - 1
+                };
 
-                    // Cleanup the non-deterministic states removing the non-deterministic
-                    // transitions
-                    if let Some(ts) = self.transitions.get_mut(&s) {
-                        let mut dets = HashSet::new();
-
-                        for d in ts.drain() {
-                            if d.0 == *c {
-                                // Wipe out non-deterministic transitions to Vec
-                                ndtrans.push(d);
-                            } else {
-                                // Hold deterministic ones
-                                dets.insert(d);
-                            }
-                        }
-
-                        // Put deterministic transitions back
-                        mem::replace(ts, dets);
-                    }
+                indexes.insert(subset.clone(), index);
+                next.push_back(subset);
 
-                    // In each ND-Transition, create a transition to the new state
-                    self.create_transition_between(&s, &newstate, c.clone());
-                    // Map this state its transitions
-                    new_states.insert(newstate, ndtrans);
-                }
-            }
+                index
+            };
 
-            // After all states are mapped then we could create their transitions, else
-            // inconsistent transitions may be mapped making determinization worthless
-            for (ns, ts) in new_states {
-                // Check if any of the states is 
-                let superstate = {
-                    let mut state = None;
-                    let mut ss = HashSet::new();
+            while let Some(subset) = next.pop_front() {
+                let from = index_of(subset.clone(), &mut next);
+                let mut moves: BTreeMap<&T, BTreeSet<usize>> = BTreeMap::new();
 
-                    for ndt in &ts {
-                        if state_map.contains_key(&ndt.1) {
-                            ss = ss.union(&state_map[&ndt.1]).cloned().collect();
-                        }
+                for s in &subset {
+                    for t in self.transitions.get(s).into_iter().flatten() {
+                        moves.entry(&t.0).or_default().insert(t.1);
                     }
+                }
 
-                    for ndt in &ts {
-                        if state_map.contains_key(&ndt.1) && ss == state_map[&ndt.1] {
-                            state = Some(ndt.1);
-                            break;
-                        }
-                    }
+                for (by, to) in moves {
+                    let dest = index_of(self.epsilon_closure(&to), &mut next);
 
-                    state
-                };
+                    transitions.entry(from).or_default().insert(Transition(by.clone(), dest));
+                }
+            }
 
-                let new_state_transitions = {
-                    let mut trans = Vec::new();
+            self.current = if indexes.values().any(|i| *i == self.current) { self.current } else { initial };
+        }
 
-                    if let Some(ss) = superstate {
-                        for t in &self.transitions[&ss] {
-                            trans.push(t.clone());
-                        }
-                    } else {
-                        for ndt in ts {
-                            // Add relationed states transitions
-                            if let Some(ts) = self.transitions.get(&ndt.1) {
-                                for t in ts {
-                                    trans.push(t.clone());
-                                }
-                            }
-                        }
-                    }
+        let mut states = BTreeMap::new();
+        let mut winners = Vec::new();
 
-                    trans
-                };
+        for (subset, index) in &indexes {
+            states.insert(*index, subset.iter().any(|s| self.state_accept(*s)));
+
+            if subset.len() > 1 || !subset.contains(index) {
+                winners.push((*index, self.label_winner(subset)));
+            }
+        }
 
-                for dt in new_state_transitions {
-                    self.add_transition_to(&ns, dt);
+        for (index, winner) in winners {
+            match winner {
+                Some(winner) => self.inherit_label(index, winner),
+                None => {
+                    self.labels.remove(&index);
+                    self.priorities.remove(&index);
                 }
             }
         }
+
+        self.labels.retain(|s, _| states.contains_key(s));
+        self.priorities.retain(|s, _| states.contains_key(s));
+        self.states = states;
+        self.transitions = transitions;
+        self.epsilon.clear();
+
+        true
     }
 
-    // Would be great to use an "Iterator" to BFS
-    pub fn get_unreachable_states(&self) -> Vec<usize> {
-        let mut unreached: Vec<usize> = self.states.keys().cloned().collect();
-        let mut current: usize;
+    /// States reachable from the initial one, following transitions and epsilon transitions
+    pub fn reachable_states(&self) -> BTreeSet<usize> {
+        let mut reached = BTreeSet::new();
         let mut next = VecDeque::new();
 
-        // Using binary seach requires a sorted vec
-        unreached.sort();
-        
-        next.push_back(self.initial().to_owned());
+        reached.insert(self.initial);
+        next.push_back(self.initial);
 
         // "BFS"
-        while !unreached.is_empty() && !next.is_empty() {
-            current = next.pop_front().unwrap();
+        while let Some(current) = next.pop_front() {
+            let by_symbol = self.transitions.get(&current).into_iter().flatten().map(|t| t.1);
+            let by_epsilon = self.epsilon.get(&current).into_iter().flatten().cloned();
 
-            if let Some(ts) = self.transitions.get(&current) {
-                for t in ts {
-                    if unreached.binary_search(&t.1).is_ok() {
-                        next.push_back(t.1);
-                    }
+            for dest in by_symbol.chain(by_epsilon) {
+                if reached.insert(dest) {
+                    next.push_back(dest);
                 }
             }
-
-            unreached.remove_item(&current);
         }
 
-        unreached
+        reached
+    }
+
+    /// States not reachable from the initial one, sorted
+    pub fn get_unreachable_states(&self) -> Vec<usize> {
+        let reached = self.reachable_states();
+
+        self.states.keys().filter(|s| !reached.contains(s)).cloned().collect()
+    }
+
+    /// Whether the DFA accepts no input at all, i.e. no accepting state is reachable
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        let reached = self.reachable_states();
+
+        self.accepting_states()
+            .iter()
+            .all(|s| !reached.contains(s))
     }
 
+    /// States from which no accepting state can be reached, sorted. Found walking backwards
+    /// from the accepting states, whatever is not reached that way is dead
     pub fn get_dead_states(&self) -> Vec<usize> {
-        let mut unvisited: Vec<usize> = self.states.keys().cloned().collect();
-        let mut dead: Vec<usize>;
-        // The current path of DFS
-        let mut path: Vec<usize> = Vec::new();
-        // (path, stacked_by)
-        let mut stack: Vec<(usize, usize)> = vec![
-            (self.initial().to_owned(), self.initial().to_owned())
-        ];
-
-        // Using binary seach requires a sorted vec
-        unvisited.sort();
-        dead = unvisited.clone();
-
-        // "DFS"
-        while !dead.is_empty() && !stack.is_empty() {
-            let (current, stacked_by) = stack.pop().unwrap();
-
-            // Check and correct path
-            while let Some(last_in_path) = path.iter().last().cloned() {
-                if stacked_by != last_in_path { path.pop(); }
-                else { break; }
-            }
-
-            path.push(current);
-
-            if let Some(trans) = self.transitions.get(&current) {
-                for t in trans {
-                    // Check if any neighbour accept or is not dead, if so, remove it from dead
-                    // states and set the whole path as non-dead
-                    if self.state_accept(t.1) || dead.binary_search(&t.1).is_err() {
-                        dead.remove_item(&t.1);
-                        for s in &path { dead.remove_item(s); }
-                    }
+        let mut reverse: HashMap<usize, Vec<usize>> = HashMap::new();
+
+        for (from, ts) in &self.transitions {
+            for t in ts {
+                reverse.entry(t.1).or_default().push(*from);
+            }
+        }
+
+        for (from, es) in &self.epsilon {
+            for e in es {
+                reverse.entry(*e).or_default().push(*from);
+            }
+        }
+
+        let mut live: HashSet<usize> = self.accepting_states().into_iter().collect();
+        let mut next: VecDeque<usize> = live.iter().cloned().collect();
 
-                    // Stack neighbours that were not visited
-                    if unvisited.iter().any(|x| x == &t.1) {
-                        unvisited.remove_item(&t.1);
-                        stack.push((t.1, current));
+        while let Some(current) = next.pop_front() {
+            if let Some(origins) = reverse.get(&current) {
+                for o in origins {
+                    if live.insert(*o) {
+                        next.push_back(*o);
                     }
                 }
             }
         }
 
-        dead
+        self.states
+            .keys()
+            .filter(|s| !live.contains(s))
+            .cloned()
+            .collect()
     }
 
     pub fn remove_unreachable_states(&mut self) {
@@ -450,118 +1088,2597 @@ impl<T: Transitable + Debug> Dfa<T> {
         }
     }
 
+    /// Remove the trap states and the transitions into them, leaving the DFA partial
+    #[allow(dead_code)]
+    pub fn remove_trap_states(&mut self) {
+        for state in self.trap_states() {
+            self.remove_state(state);
+        }
+    }
+
+    /// Remove unreachable and dead states, rewinding if the current state was one of them, and
+    /// the symbols no transition uses anymore
     pub fn minimize(&mut self) {
         self.remove_unreachable_states();
         self.remove_dead_states();
+        self.prune_alphabet();
     }
 
-    pub fn insert_error_state(&mut self) {
-        let error_state    = self.add_state(true);
-        let states: Vec<_> = self.states.keys().cloned().collect();
-        let alphabet: HashSet<_> = self.alphabet.iter().cloned().collect();
+    /// Hopcroft's partition refinement: split states in accepting and non-accepting classes and
+    /// keep splitting classes whose states go to different classes by some symbol. Panics if the
+    /// DFA is not deterministic and complete
+    fn hopcroft_partition(&self) -> Vec<BTreeSet<usize>> {
+        assert!(
+            self.is_deterministic(),
+            "Hopcroft minimization requires a deterministic automaton"
+        );
+
+        let alphabet: Vec<T> = self.alphabet.iter().cloned().collect();
+        let dest = |state: &usize, by: &T| -> usize {
+            self.transition(*state, by).expect("Hopcroft minimization requires a complete automaton")
+        };
+
+        // States with different acceptance, token names or priorities are never equivalent
+        let mut classes: HashMap<(bool, Option<&String>, i32), BTreeSet<usize>> = HashMap::new();
+
+        for s in self.states.keys() {
+            classes.entry((self.state_accept(*s), self.labels.get(s), self.priority(*s)))
+                .or_default()
+                .insert(*s);
+        }
 
-        info!("Error State: {}", error_state);
+        let mut partition: Vec<BTreeSet<usize>> = classes.into_iter().map(|c| c.1).collect();
 
-        for state in states {
-            let transitions_by = { 
-                let transitions = self.transitions.entry(state).or_insert_with(HashSet::new);
-                transitions.iter().map(|x| x.0.clone()).collect()
-            };
+        let mut waiting = partition.clone();
 
-            let missing = alphabet.difference(&transitions_by);
+        while let Some(splitter) = waiting.pop() {
+            for c in &alphabet {
+                // States that go into the splitter by `c`
+                let into: BTreeSet<usize> = self.states
+                    .keys()
+                    .filter(|s| splitter.contains(&dest(s, c)))
+                    .cloned()
+                    .collect();
 
-            debug!("Missing on {}: {:?}", state, missing);
+                let mut refined = Vec::with_capacity(partition.len());
 
-            for ch in missing {
-                self.create_transition_between(&state, &error_state, ch.to_owned());
+                for class in partition.drain(..) {
+                    let inside: BTreeSet<usize>  = class.intersection(&into).cloned().collect();
+                    let outside: BTreeSet<usize> = class.difference(&into).cloned().collect();
+
+                    if inside.is_empty() || outside.is_empty() {
+                        refined.push(class);
+                        continue;
+                    }
+
+                    // A waiting class is replaced by both halves, otherwise only the smaller
+                    // one is enough to split the others
+                    if let Some(pos) = waiting.iter().position(|w| w == &class) {
+                        waiting.remove(pos);
+                        waiting.push(inside.clone());
+                        waiting.push(outside.clone());
+                    } else if inside.len() <= outside.len() {
+                        waiting.push(inside.clone());
+                    } else {
+                        waiting.push(outside.clone());
+                    }
+
+                    refined.push(inside);
+                    refined.push(outside);
+                }
+
+                partition = refined;
             }
         }
+
+        partition
     }
-}
 
-impl<T: Display + Debug + Eq + Hash + Ord> Dfa<T> {
-    pub fn to_dot(&self) -> String {
-        let mut dot = String::from("digraph FA {\nrankdir=\"LR\";\n");
-        let alphabet: Vec<&T>   = { let mut a = self.alphabet.iter().collect::<Vec<_>>(); a.sort(); a };
-        let states: Vec<&usize> = { let mut s = self.states.keys().collect::<Vec<_>>(); s.sort(); s };
+    /// Classes of equivalent states, the partition `minimize_hopcroft` merges, sorted by their
+    /// smallest state. Missing transitions count as going to a trap state, which is left out of
+    /// the classes unless the DFA already had it. Panics if the DFA is not deterministic
+    #[allow(dead_code)]
+    pub fn equivalence_classes(&self) -> Vec<HashSet<usize>> {
+        let mut complete = self.clone();
+        let trap = complete.complete();
+        let added = !self.states.contains_key(&trap);
 
-        for state in states {
-            if self.state_accept(state.to_owned()) {
-                dot += format!("{} [shape=doublecircle];\n", state).as_str();
+        let mut partition = complete.hopcroft_partition();
+
+        if added {
+            for class in &mut partition {
+                class.remove(&trap);
             }
+        }
 
-            for s in &alphabet {
-                if let Some(transitions) = self.transitions.get(state) {
-                    let mut ts = "{".to_string();
+        partition.retain(|class| !class.is_empty());
+        partition.sort();
 
-                    for t in transitions.iter() {
-                        if &&t.0 == s {
-                            if ts.len() > 1 { ts.push(','); }
-                            ts += format!("{}", t.1).as_str();
-                        }
-                    }
+        partition.into_iter().map(|class| class.into_iter().collect()).collect()
+    }
+
+    /// Merge the states Hopcroft's partition finds equivalent into one of them, without
+    /// removing unreachable or dead states first. Returns the representative of every old
+    /// state, the initial state represents its own class, and the current state moves to its
+    /// representative. The DFA must be deterministic and complete
+    #[allow(dead_code)]
+    pub fn merge_equivalent_states(&mut self) -> HashMap<usize, usize> {
+        let mut representative: HashMap<usize, usize> = HashMap::new();
+
+        for class in self.hopcroft_partition() {
+            let rep = if class.contains(&self.initial) {
+                self.initial
+            } else {
+                *class.iter().next().unwrap()
+            };
 
-                    ts.push('}');
+            for s in class {
+                representative.insert(s, rep);
+            }
+        }
 
-                    if ts.len() > 2 {
-                        dot += format!("{} -> {} [label={}];\n", state, ts, s).as_str();
-                    }
-                }
+        let transitions = mem::take(&mut self.transitions);
+
+        self.states.retain(|s, _| representative[s] == *s);
+        self.labels.retain(|s, _| representative[s] == *s);
+        self.priorities.retain(|s, _| representative[s] == *s);
+
+        for (s, ts) in transitions {
+            if representative[&s] != s { continue; }
+
+            for t in ts {
+                let dest = representative[&t.1];
+                self.add_transition_to(&s, Transition::new(t.0, dest));
             }
         }
 
-        dot.push_str("}\n");
+        // The current state may be gone after a removal, rewind then
+        self.current = representative.get(&self.current).cloned().unwrap_or(self.initial);
 
-        dot
+        representative
     }
 
-    pub fn to_csv(&self) -> String {
-        let mut csv = String::from("State");
-        let mut alphabet: Vec<&T> = self.alphabet.iter().collect();
-        let mut states: Vec<&usize> = self.states.keys().collect();
+    /// Minimize the DFA merging all equivalent states into one (the initial state keeps its
+    /// index). The DFA must be deterministic and complete, so call `determinize` and
+    /// `insert_error_state` first
+    #[allow(dead_code)]
+    pub fn minimize_hopcroft(&mut self) {
+        self.merge_equivalent_states();
+    }
 
-        alphabet.sort();
-        states.sort();
+    /// Minimal DFA of the same language, leaving this one untouched: a copy is determinized,
+    /// completed with an error state and minimized by Hopcroft's algorithm. An empty language
+    /// gives a single non-accepting initial state without transitions
+    #[allow(dead_code)]
+    pub fn minimal(&self) -> Dfa<T> {
+        let mut minimal = self.clone();
 
-        // Header
-        for a in &alphabet {
-            csv += format!(",{}", a).as_str();
+        minimal.determinize();
+        minimal.minimize();
+
+        // Everything is dead, the initial state included
+        if !minimal.states.contains_key(&minimal.initial) {
+            return Dfa::from_transitions(minimal.initial, &[], &[]);
         }
 
-        csv.push('\n');
+        minimal.insert_error_state();
+        minimal.minimize_hopcroft();
+        // The error state is left alone if the DFA was already complete
+        minimal.remove_unreachable_states();
 
-        for k in &states {
-            let accept = &self.states[k];
+        minimal
+    }
 
-            if *k == self.initial() { csv.push_str("->"); }
-            if accept.to_owned() { csv.push('*'); }
+    /// The `minimal` DFA with its states renumbered in BFS order from the initial state, taking
+    /// transitions in symbol order. DFAs of the same language (and tokens) get equal canonical
+    /// forms, so comparing them checks equivalence
+    #[allow(dead_code)]
+    pub fn canonical_form(&self) -> Dfa<T> {
+        let minimal = self.minimal();
+        let mut order: HashMap<usize, usize> = HashMap::new();
+        let mut next = VecDeque::new();
 
-            csv += format!("<{}>", k).as_str();
+        order.insert(minimal.initial, 0);
+        next.push_back(minimal.initial);
 
-            for a in &alphabet {
-                match self.transitions.get(k) {
-                    Some(trans) => {
-                        let mut has_states = false;
-
-                        for t in trans {
-                            if t.0 == **a {
-                                // Controls the first comma
-                                if ! has_states { csv.push(','); has_states = true; }
-                                csv += format!("<{}>", t.1).as_str();
-                            }
-                        }
-
-                        if ! has_states {
-                            csv.push_str(",-");
-                        }
-                    },
-                    None => csv.push_str(",-")
+        while let Some(state) = next.pop_front() {
+            for t in minimal.transitions.get(&state).into_iter().flatten() {
+                if !order.contains_key(&t.1) {
+                    let index = order.len();
+
+                    order.insert(t.1, index);
+                    next.push_back(t.1);
                 }
             }
+        }
 
-            csv.push('\n');
+        let mut canonical = Dfa::new();
+
+        canonical.states = minimal.states.iter().map(|(s, a)| (order[s], *a)).collect();
+        canonical.labels = minimal.labels.iter().map(|(s, l)| (order[s], l.clone())).collect();
+        canonical.priorities = minimal.priorities.iter().map(|(s, p)| (order[s], *p)).collect();
+
+        for (from, ts) in &minimal.transitions {
+            for t in ts {
+                canonical.create_transition_between(&order[from], &order[&t.1], t.0.clone());
+            }
         }
 
-        csv
+        canonical
+    }
+
+    /// DFA of the reversed language, with only reachable states: `reverse` followed by
+    /// `determinize`. The new initial state of `reverse` only adds itself to the set of the old
+    /// accepting states, so when that set is also reached on its own both get the same
+    /// transitions and the initial one is dropped for it, as Brzozowski's minimization needs
+    fn reverse_determinized(&self) -> Dfa<T> {
+        let mut reversed = self.reverse();
+
+        reversed.determinize();
+        reversed.remove_unreachable_states();
+
+        let initial = reversed.initial;
+        let twin = reversed.states
+            .keys()
+            .find(|s| {
+                **s != initial
+                    && reversed.state_accept(**s) == reversed.state_accept(initial)
+                    && reversed.transitions.get(s) == reversed.transitions.get(&initial)
+            })
+            .cloned();
+
+        // Nothing goes back to the initial state, it is only the start
+        if let Some(twin) = twin {
+            reversed.remove_state(initial);
+            reversed.set_initial(twin);
+            reversed.rewind();
+        }
+
+        reversed
+    }
+
+    /// Brzozowski's minimization: reverse and determinize twice, which leaves the minimal DFA
+    /// without dead states. No error state is added, and labels and priorities are lost
+    #[allow(dead_code)]
+    pub fn minimize_brzozowski(&mut self) {
+        *self = self.reverse_determinized().reverse_determinized();
+    }
+
+    /// Renumber the states to `0..n`, keeping their order but with the initial state as 0.
+    /// Useful after removals leave the indexes sparse. Unused symbols leave the alphabet
+    #[allow(dead_code)]
+    pub fn normalize(&mut self) {
+        let mut order: Vec<usize> = self.states.keys().cloned().filter(|s| *s != self.initial).collect();
+        order.insert(0, self.initial);
+
+        let remap: HashMap<usize, usize> = order.into_iter().enumerate().map(|(i, s)| (s, i)).collect();
+        let transitions = mem::take(&mut self.transitions);
+        let epsilon = mem::take(&mut self.epsilon);
+
+        self.states = self.states.iter().map(|(s, a)| (remap[s], *a)).collect();
+        self.labels = self.labels.drain().map(|(s, l)| (remap[&s], l)).collect();
+        self.priorities = self.priorities.drain().map(|(s, p)| (remap[&s], p)).collect();
+
+        for (from, ts) in transitions {
+            for t in ts {
+                self.add_transition_to(&remap[&from], Transition::new(t.0, remap[&t.1]));
+            }
+        }
+
+        for (from, es) in epsilon {
+            for e in es {
+                self.create_epsilon_transition_between(&remap[&from], &remap[&e]);
+            }
+        }
+
+        self.prune_alphabet();
+
+        // The current state may be gone after a removal, rewind then
+        self.initial = 0;
+        self.current = remap.get(&self.current).cloned().unwrap_or(0);
+    }
+
+    /// Turn the DFA into one accepting exactly the inputs it rejected. It is determinized and
+    /// completed with an error state first, so missing transitions become accepted
+    #[allow(dead_code)]
+    pub fn complement(&mut self) {
+        self.determinize();
+        self.complete();
+
+        for accept in self.states.values_mut() {
+            *accept = !*accept;
+        }
+    }
+
+    /// Product construction over the union of both alphabets, a product state accepts when
+    /// `accept` says so given the acceptance of each side. A missing transition leads to an
+    /// implicit dead state (`None`) on its side. Both DFAs must be deterministic. Pairs are
+    /// only created when reached from the initial one, so the result never has unreachable
+    /// states and needs no `remove_unreachable_states`
+    #[allow(dead_code)]
+    pub fn product<F: Fn(bool, bool) -> bool>(&self, other: &Dfa<T>, accept: F) -> Dfa<T> {
+        let alphabet: Vec<T> = self.alphabet.union(&other.alphabet).cloned().collect();
+        let step = |dfa: &Dfa<T>, state: Option<usize>, by: &T| -> Option<usize> {
+            state.and_then(|s| dfa.transition(s, by))
+        };
+        let accepts = |pair: &(Option<usize>, Option<usize>)| -> bool {
+            accept(
+                pair.0.is_some_and(|s| self.state_accept(s)),
+                pair.1.is_some_and(|s| other.state_accept(s))
+            )
+        };
+        // Both sides dead is only worth a state if it accepts
+        let keep_dead = accept(false, false);
+
+        let mut product = Dfa::new();
+        let mut mapper: HashMap<(Option<usize>, Option<usize>), usize> = HashMap::new();
+        let mut next = VecDeque::new();
+        let start = (Some(self.initial), Some(other.initial));
+
+        product.set_current_state_accept(accepts(&start));
+        mapper.insert(start, product.initial);
+        next.push_back(start);
+
+        while let Some(pair) = next.pop_front() {
+            let from = mapper[&pair];
+
+            for c in &alphabet {
+                let dest = (step(self, pair.0, c), step(other, pair.1, c));
+
+                if dest == (None, None) && !keep_dead { continue; }
+
+                let index = if let Some(index) = mapper.get(&dest) { *index } else {
+                    let index = product.add_state(accepts(&dest));
+
+                    mapper.insert(dest, index);
+                    next.push_back(dest);
+
+                    index
+                };
+
+                product.create_transition_between(&from, &index, c.clone());
+            }
+        }
+
+        product
+    }
+
+    /// DFA accepting the inputs accepted by both `self` and `other`
+    #[allow(dead_code)]
+    pub fn intersection(&self, other: &Dfa<T>) -> Dfa<T> {
+        self.product(other, |a, b| a && b)
+    }
+
+    /// DFA accepting the inputs accepted by `self` or `other`, e.g. to merge token DFAs into a
+    /// single scanner
+    #[allow(dead_code)]
+    pub fn union(&self, other: &Dfa<T>) -> Dfa<T> {
+        self.product(other, |a, b| a || b)
+    }
+
+    /// DFA accepting the inputs accepted by `self` but not by `other`
+    #[allow(dead_code)]
+    pub fn difference(&self, other: &Dfa<T>) -> Dfa<T> {
+        self.product(other, |a, b| a && !b)
+    }
+
+    /// Whether both DFAs accept exactly the same inputs: the symmetric difference of their
+    /// languages must be empty. Works on determinized copies, so neither DFA is changed
+    #[allow(dead_code)]
+    pub fn equivalent(&self, other: &Dfa<T>) -> bool {
+        let mut left = self.clone();
+        let mut right = other.clone();
+
+        left.determinize();
+        right.determinize();
+
+        left.product(&right, |a, b| a != b).is_empty()
+    }
+
+    /// Whether every input accepted by `self` is also accepted by `other`, i.e. their
+    /// difference is empty. Works on determinized copies, so neither DFA is changed
+    #[allow(dead_code)]
+    pub fn is_subset(&self, other: &Dfa<T>) -> bool {
+        let mut left = self.clone();
+        let mut right = other.clone();
+
+        left.determinize();
+        right.determinize();
+
+        left.difference(&right).is_empty()
+    }
+
+    /// Amount of distinct inputs accepted, or `None` if there are infinitely many: a cycle both
+    /// reachable and reaching an accepting state. Counted as paths on a determinized copy
+    /// without unreachable and dead states, so each input is a single path. Saturates at
+    /// `usize::MAX`
+    #[allow(dead_code)]
+    pub fn language_size(&self) -> Option<usize> {
+        let mut dfa = self.clone();
+
+        dfa.determinize();
+        dfa.minimize();
+
+        // Everything is dead, the initial state included
+        if !dfa.states.contains_key(&dfa.initial) {
+            return Some(0);
+        }
+
+        dfa.count_paths(dfa.initial, &mut HashMap::new(), &mut HashSet::new())
+    }
+
+    /// Paths from `state` to an accepting state, `None` if one of them has a cycle. `visiting`
+    /// are the states in the current path, `counted` the ones already done
+    fn count_paths(&self, state: usize, counted: &mut HashMap<usize, usize>, visiting: &mut HashSet<usize>) -> Option<usize> {
+        if let Some(&n) = counted.get(&state) {
+            return Some(n);
+        }
+
+        if !visiting.insert(state) {
+            return None;
+        }
+
+        let mut n = if self.state_accept(state) { 1 } else { 0 };
+
+        for (_, dest) in self.transitions_from(state) {
+            n = self.count_paths(dest, counted, visiting)?.saturating_add(n);
+        }
+
+        visiting.remove(&state);
+        counted.insert(state, n);
+
+        Some(n)
+    }
+
+    /// NFA accepting an input of `self` followed by one of `other`: the states of `other` are
+    /// renumbered after the ones of `self`, and the accepting states of `self` get epsilon
+    /// transitions to its initial state instead of accepting. Labels of `other` are kept
+    #[allow(dead_code)]
+    pub fn concat(&self, other: &Dfa<T>) -> Dfa<T> {
+        let mut concat = self.clone();
+        let offset = self.states.keys().max().map_or(0, |s| s + 1);
+        let accepting = self.accepting_states();
+
+        concat.labels.clear();
+        concat.priorities.clear();
+
+        for accept in concat.states.values_mut() {
+            *accept = false;
+        }
+
+        for (s, accept) in &other.states {
+            concat.states.insert(s + offset, *accept);
+        }
+
+        for (from, ts) in &other.transitions {
+            for t in ts {
+                concat.add_transition_to(&(from + offset), Transition::new(t.0.clone(), t.1 + offset));
+            }
+        }
+
+        for (from, es) in &other.epsilon {
+            for e in es {
+                concat.create_epsilon_transition_between(&(from + offset), &(e + offset));
+            }
+        }
+
+        concat.alphabet.extend(other.alphabet.iter().cloned());
+        concat.labels.extend(other.labels.iter().map(|(s, l)| (s + offset, l.clone())));
+        concat.priorities.extend(other.priorities.iter().map(|(s, p)| (s + offset, *p)));
+
+        for s in accepting {
+            concat.create_epsilon_transition_between(&s, &(other.initial + offset));
+        }
+
+        concat.rewind();
+
+        concat
+    }
+
+    /// Turn the DFA into an NFA of the Kleene star of its language: accepting states get epsilon
+    /// transitions back to the initial state, and a new accepting initial state takes the
+    /// empty input. No transition goes into that new state, so it accepts nothing else
+    #[allow(dead_code)]
+    pub fn kleene_star(&mut self) {
+        let old_initial = self.initial;
+
+        for s in self.accepting_states() {
+            self.create_epsilon_transition_between(&s, &old_initial);
+        }
+
+        let initial = self.add_state(true);
+
+        self.create_epsilon_transition_between(&initial, &old_initial);
+        self.set_initial(initial);
+        self.rewind();
+    }
+
+    /// NFA of the reversed language: every transition is flipped, the old initial state is the
+    /// only accepting one and a new initial state has epsilon transitions to the old accepting
+    /// states. Labels and priorities are not kept
+    #[allow(dead_code)]
+    pub fn reverse(&self) -> Dfa<T> {
+        let mut reversed = Dfa::new();
+
+        reversed.states = self.states.keys().map(|s| (*s, *s == self.initial)).collect();
+        reversed.alphabet = self.alphabet.clone();
+
+        for (from, ts) in &self.transitions {
+            for t in ts {
+                reversed.add_transition_to(&t.1, Transition::new(t.0.clone(), *from));
+            }
+        }
+
+        for (from, es) in &self.epsilon {
+            for e in es {
+                reversed.create_epsilon_transition_between(e, from);
+            }
+        }
+
+        let initial = reversed.add_state(false);
+
+        for (s, accept) in &self.states {
+            if *accept {
+                reversed.create_epsilon_transition_between(&initial, s);
+            }
+        }
+
+        reversed.set_initial(initial);
+        reversed.rewind();
+
+        reversed
+    }
+
+    /// Make the DFA total: every missing transition goes to a non-accepting error state,
+    /// which loops to itself by every symbol. See `complete`
+    pub fn insert_error_state(&mut self) {
+        let error_state = self.complete();
+
+        info!("Error State: {}", error_state);
+    }
+
+    /// Make the DFA total and return its trap state, where every missing transition now goes.
+    /// An existing trap state is used if there is one (see `trap_states`), else a new one is
+    /// added, even if nothing was missing, so there is always one to refer to
+    pub fn complete(&mut self) -> usize {
+        let trap = match self.trap_states().first() {
+            Some(trap) => *trap,
+            None => self.add_state(false)
+        };
+
+        // A new trap misses every symbol, so it gets its self-loops here
+        for (state, missing) in self.missing_transitions() {
+            debug!("Missing on {}: {:?}", state, missing);
+
+            for ch in missing {
+                self.create_transition_between(&state, &trap, ch);
+            }
+        }
+
+        trap
+    }
+
+    /// Symbols of the alphabet each state has no transition by, only states missing some
+    pub fn missing_transitions(&self) -> BTreeMap<usize, BTreeSet<T>> {
+        let mut gaps = BTreeMap::new();
+
+        for state in self.states.keys() {
+            let missing: BTreeSet<T> = self.alphabet
+                .iter()
+                .filter(|c| self.transition(*state, c).is_none())
+                .cloned()
+                .collect();
+
+            if !missing.is_empty() {
+                gaps.insert(*state, missing);
+            }
+        }
+
+        gaps
+    }
+
+    /// Transition table of a deterministic DFA: the sorted alphabet, for each state in sorted
+    /// order the destination by each symbol, and whether each of those states accepts. Cells
+    /// are state indexes, which match rows after `normalize`
+    #[allow(dead_code)]
+    pub fn to_transition_table(&self) -> (Vec<T>, Vec<Vec<Option<usize>>>, Vec<bool>) {
+        let alphabet: Vec<T> = self.alphabet.iter().cloned().collect();
+        let table = self.states
+            .keys()
+            .map(|s| alphabet.iter().map(|c| self.transition(*s, c)).collect())
+            .collect();
+        let accept = self.states.values().cloned().collect();
+
+        (alphabet, table, accept)
+    }
+
+    /// Whether every state has a transition by every symbol of the alphabet
+    #[allow(dead_code)]
+    pub fn is_complete(&self) -> bool {
+        self.missing_transitions().is_empty()
+    }
+
+    /// Amount of transitions leaving `state`, epsilon transitions included
+    #[allow(dead_code)]
+    pub fn out_degree(&self, state: usize) -> usize {
+        self.transitions.get(&state).map_or(0, |ts| ts.len())
+            + self.epsilon.get(&state).map_or(0, |es| es.len())
+    }
+
+    /// Amount of transitions arriving at `state`, epsilon transitions included
+    #[allow(dead_code)]
+    pub fn in_degree(&self, state: usize) -> usize {
+        self.transitions_to(state).count()
+            + self.epsilon.values().filter(|es| es.contains(&state)).count()
+    }
+
+    /// Counts describing the DFA, e.g. to report how big a generated one is
+    #[allow(dead_code)]
+    pub fn stats(&self) -> DfaStats {
+        DfaStats {
+            num_states: self.num_states(),
+            num_transitions: self.num_transitions(),
+            num_epsilon_transitions: self.epsilon.values().map(|es| es.len()).sum(),
+            num_accepting: self.accepting_states().len(),
+            alphabet_size: self.alphabet.len(),
+            deterministic: self.is_deterministic(),
+            complete: self.is_complete()
+        }
+    }
+}
+
+impl<T: Transitable> Dfa<T> {
+    /// Transitions leaving `state` sorted by symbol, then destination
+    fn sorted_transitions_of(&self, state: &usize) -> Vec<&Transition<T>> {
+        self.transitions
+            .get(state)
+            .map(|ts| ts.iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// The shortest input accepted by the DFA (ties broken by the symbols order) or `None` if
+    /// it accepts nothing. Epsilon transitions are not followed, so determinize first
+    #[allow(dead_code)]
+    pub fn shortest_accepted(&self) -> Option<Vec<T>> {
+        let mut parent: HashMap<usize, (usize, T)> = HashMap::new();
+        let mut visited = HashSet::new();
+        let mut next = VecDeque::new();
+        let mut found = None;
+
+        visited.insert(self.initial);
+        next.push_back(self.initial);
+
+        // BFS, so the first accepting state found is the closest one
+        while let Some(current) = next.pop_front() {
+            if self.state_accept(current) {
+                found = Some(current);
+                break;
+            }
+
+            for t in self.sorted_transitions_of(&current) {
+                if visited.insert(t.1) {
+                    parent.insert(t.1, (current, t.0.clone()));
+                    next.push_back(t.1);
+                }
+            }
+        }
+
+        found.map(|mut state| {
+            let mut word = Vec::new();
+
+            while let Some(&(prev, ref by)) = parent.get(&state) {
+                word.push(by.clone());
+                state = prev;
+            }
+
+            word.reverse();
+            word
+        })
+    }
+
+    /// Copy of the DFA with every symbol replaced by `f` of it, e.g. to lowercase all chars.
+    /// Symbols `f` maps to the same one may leave a state with two transitions by it, so the
+    /// result may need to be determinized again
+    #[allow(dead_code)]
+    pub fn map_symbols<U: Transitable, F: Fn(&T) -> U>(&self, f: F) -> Dfa<U> {
+        let transitions = self.transitions
+            .iter()
+            .map(|(from, ts)| (*from, ts.iter().map(|t| Transition(f(&t.0), t.1)).collect()))
+            .collect();
+
+        Dfa {
+            states: self.states.clone(),
+            initial: self.initial,
+            current: self.current,
+            transitions,
+            epsilon: self.epsilon.clone(),
+            alphabet: self.alphabet.iter().map(&f).collect(),
+            labels: self.labels.clone(),
+            priorities: self.priorities.clone(),
+            hide_trap: self.hide_trap
+        }
+    }
+
+    /// One shortest input reaching each reachable state (ties broken by the symbols order),
+    /// shortest first: the paths of a BFS spanning tree from the initial state. Epsilon
+    /// transitions are not followed, so determinize first
+    #[allow(dead_code)]
+    pub fn coverage_strings(&self) -> Vec<Vec<T>> {
+        let mut words: HashMap<usize, Vec<T>> = HashMap::new();
+        let mut order = Vec::new();
+        let mut next = VecDeque::new();
+
+        words.insert(self.initial, Vec::new());
+        next.push_back(self.initial);
+
+        while let Some(current) = next.pop_front() {
+            order.push(current);
+
+            for t in self.sorted_transitions_of(&current) {
+                if !words.contains_key(&t.1) {
+                    let mut longer = words[&current].clone();
+                    longer.push(t.0.clone());
+
+                    words.insert(t.1, longer);
+                    next.push_back(t.1);
+                }
+            }
+        }
+
+        order.into_iter().map(|s| words.remove(&s).expect("Every visited state has a word")).collect()
+    }
+
+    /// A random accepted input of at most `max_steps` symbols, or `None` if every accepting
+    /// state is farther than that. It walks from the initial state, at each step either
+    /// stopping, if the state accepts, or following a random transition to a state an
+    /// accepting one is still reachable from within the steps left. Epsilon transitions are
+    /// not followed, so determinize first
+    #[cfg(feature = "rand")]
+    #[allow(dead_code)]
+    pub fn sample<R: Rng>(&self, rng: &mut R, max_steps: usize) -> Option<Vec<T>> {
+        // Fewest steps from each state to an accepting one, by a BFS backwards
+        let mut distance: HashMap<usize, usize> = HashMap::new();
+        let mut reverse: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut next = VecDeque::new();
+
+        for (from, _, to) in self.iter_transitions() {
+            reverse.entry(to).or_default().push(from);
+        }
+
+        for s in self.states.keys().filter(|s| self.state_accept(**s)) {
+            distance.insert(*s, 0);
+            next.push_back(*s);
+        }
+
+        while let Some(current) = next.pop_front() {
+            let d = distance[&current] + 1;
+
+            for from in reverse.get(&current).into_iter().flatten() {
+                if !distance.contains_key(from) {
+                    distance.insert(*from, d);
+                    next.push_back(*from);
+                }
+            }
+        }
+
+        if distance.get(&self.initial).is_none_or(|d| *d > max_steps) {
+            return None;
+        }
+
+        let mut state = self.initial;
+        let mut word = Vec::new();
+
+        // The state is always at most as far from accepting as the steps left
+        loop {
+            let left = max_steps - word.len();
+            let options: Vec<&Transition<T>> = self.sorted_transitions_of(&state)
+                .into_iter()
+                .filter(|t| distance.get(&t.1).is_some_and(|d| *d < left))
+                .collect();
+            // Stopping is one more option in accepting states
+            let choice = rng.gen_range(0..options.len() + self.state_accept(state) as usize);
+
+            match options.get(choice) {
+                Some(t) => {
+                    word.push(t.0.clone());
+                    state = t.1;
+                },
+                None => return Some(word)
+            }
+        }
+    }
+
+    /// Every accepted input of length up to `max_len`, shortest first and then in the symbols
+    /// order. The bound makes it terminate on cyclic DFAs, but the amount of words still grows
+    /// exponentially with it
+    #[allow(dead_code)]
+    pub fn words_up_to(&self, max_len: usize) -> Vec<Vec<T>> {
+        let mut words = Vec::new();
+        let mut next = VecDeque::new();
+
+        next.push_back((self.initial, Vec::new()));
+
+        while let Some((state, word)) = next.pop_front() {
+            if self.state_accept(state) {
+                words.push(word.clone());
+            }
+
+            if word.len() == max_len { continue; }
+
+            for t in self.sorted_transitions_of(&state) {
+                let mut longer = word.clone();
+                longer.push(t.0.clone());
+
+                next.push_back((t.1, longer));
+            }
+        }
+
+        words
+    }
+}
+
+/// A symbol with its control chars written as `\n`, `\t`, `\r` or `\u{..}`, so it stays on
+/// a single line of the output
+fn escape_control<T: Display>(symbol: &T) -> String {
+    let mut escaped = String::new();
+
+    for c in symbol.to_string().chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            c if c.is_control() => escaped += format!("\\u{{{:x}}}", c as u32).as_str(),
+            c => escaped.push(c)
+        }
+    }
+
+    escaped
+}
+
+/// The single char `escape_control` gives `escaped` for, if any
+fn unescape_control(escaped: &str) -> Option<char> {
+    let mut chars = escaped.chars();
+
+    match (chars.next(), chars.as_str()) {
+        (Some(c), "") => Some(c),
+        (Some('\\'), "n") => Some('\n'),
+        (Some('\\'), "t") => Some('\t'),
+        (Some('\\'), "r") => Some('\r'),
+        (Some('\\'), code) if code.starts_with("u{") && code.ends_with('}') => {
+            u32::from_str_radix(&code[2..code.len() - 1], 16).ok().and_then(char::from_u32)
+        },
+        _ => None
+    }
+}
+
+/// Escape a symbol to be used inside a double-quoted DOT string, control chars are shown as
+/// their escapes rather than breaking the label
+fn escape_dot<T: Display>(symbol: &T) -> String {
+    escape_control(symbol).replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl<T: Display + Debug + Eq + Hash + Ord> Dfa<T> {
+    /// Export the DFA as Graphviz DOT. Accepting states are double circles and trap states,
+    /// like the one `complete` adds, filled boxes
+    pub fn to_dot(&self) -> String {
+        let hidden = self.hidden_states();
+        let traps = self.trap_states();
+        let mut dot = String::from("digraph FA {\nrankdir=\"LR\";\n");
+        // Invisible node pointing to the initial state
+        dot += format!("__start [shape=none, label=\"\"];\n__start -> {};\n", self.initial).as_str();
+
+        for state in self.states.keys().filter(|s| !hidden.contains(s)) {
+            if self.state_accept(state.to_owned()) {
+                dot += format!("{} [shape=doublecircle];\n", state).as_str();
+            } else if traps.contains(state) {
+                dot += format!("{} [shape=box, style=filled];\n", state).as_str();
+            }
+
+            if let Some(transitions) = self.transitions.get(state) {
+                // Symbols going to the same state share a single edge, nondeterministic
+                // transitions get a red edge of their own to each of their states, so the
+                // fan-out determinization removes is easy to spot
+                let mut edges: Vec<(usize, Vec<String>)> = Vec::new();
+                let mut fan_out: Vec<(usize, String)> = Vec::new();
+
+                for t in transitions.iter().filter(|t| !hidden.contains(&t.1)) {
+                    if transitions.iter().filter(|o| o.0 == t.0).count() > 1 {
+                        fan_out.push((t.1, escape_dot(&t.0)));
+                        continue;
+                    }
+
+                    match edges.iter().position(|e| e.0 == t.1) {
+                        Some(i) => edges[i].1.push(escape_dot(&t.0)),
+                        None    => edges.push((t.1, vec![escape_dot(&t.0)]))
+                    }
+                }
+
+                edges.sort();
+
+                for (d, labels) in edges {
+                    dot += format!("{} -> {} [label=\"{}\"];\n", state, d, labels.join(", ")).as_str();
+                }
+
+                for (d, label) in fan_out {
+                    dot += format!("{} -> {} [label=\"{}\", color=red];\n", state, d, label).as_str();
+                }
+            }
+
+            if let Some(es) = self.epsilon.get(state) {
+                for d in es {
+                    dot += format!("{} -> {} [label=\"ε\"];\n", state, d).as_str();
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+
+        dot
+    }
+
+    /// Export the DFA as a Mermaid `stateDiagram-v2`, accepting states lead to the final `[*]`.
+    /// Symbols from one state to the same destination share a single edge
+    #[allow(dead_code)]
+    pub fn to_mermaid(&self) -> String {
+        let hidden = self.hidden_states();
+        let mut mermaid = String::from("stateDiagram-v2\n");
+        mermaid += format!("    [*] --> S{}\n", self.initial).as_str();
+
+        for state in self.states.keys().filter(|s| !hidden.contains(s)) {
+            if self.state_accept(*state) {
+                mermaid += format!("    S{} --> [*]\n", state).as_str();
+            }
+
+            let mut edges: Vec<(usize, Vec<String>)> = Vec::new();
+
+            if let Some(transitions) = self.transitions.get(state) {
+                let mut ts: Vec<&Transition<T>> = transitions.iter().filter(|t| !hidden.contains(&t.1)).collect();
+                ts.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)));
+
+                for t in ts {
+                    match edges.last_mut() {
+                        Some(&mut (dest, ref mut labels)) if dest == t.1 => labels.push(escape_control(&t.0)),
+                        _ => edges.push((t.1, vec![escape_control(&t.0)]))
+                    }
+                }
+            }
+
+            if let Some(es) = self.epsilon.get(state) {
+                for d in es {
+                    edges.push((*d, vec!["ε".to_string()]));
+                }
+            }
+
+            for (dest, labels) in edges {
+                mermaid += format!("    S{} --> S{} : {}\n", state, dest, labels.join(", ")).as_str();
+            }
+        }
+
+        mermaid
+    }
+
+    pub fn to_csv(&self) -> String {
+        self.to_csv_with(CsvOptions::default())
+    }
+
+    /// Transition table as `to_csv`, laid out following `opts`
+    pub fn to_csv_with(&self, opts: CsvOptions) -> String {
+        let mut csv = String::from("State");
+        let alphabet: Vec<&T> = self.alphabet.iter().collect();
+        let hidden = self.hidden_states();
+        let states: Vec<&usize> = self.states.keys().filter(|s| !hidden.contains(s)).collect();
+        let delimiter = opts.delimiter;
+        let state = |s: usize| if opts.bracket_states { format!("<{}>", s) } else { s.to_string() };
+
+        // Header
+        if opts.accept_column {
+            csv.push(delimiter);
+            csv.push_str("Accept");
+        }
+
+        for a in &alphabet {
+            csv += format!("{}{}", delimiter, escape_control(a)).as_str();
+        }
+
+        csv.push('\n');
+
+        for k in &states {
+            let accept = self.states[k];
+
+            if *k == self.initial() { csv.push_str("->"); }
+            if accept && !opts.accept_column { csv.push('*'); }
+
+            csv += state(**k).as_str();
+
+            if opts.accept_column {
+                csv += format!("{}{}", delimiter, accept).as_str();
+            }
+
+            for a in &alphabet {
+                let dests: Vec<String> = self.transitions_from(**k)
+                    .filter(|t| t.0 == *a && !hidden.contains(&t.1))
+                    .map(|t| state(t.1))
+                    .collect();
+
+                csv.push(delimiter);
+
+                if dests.is_empty() {
+                    csv.push('-');
+                } else {
+                    // Unbracketed states need something between them
+                    csv += dests.join(if opts.bracket_states { "" } else { " " }).as_str();
+                }
+            }
+
+            csv.push('\n');
+        }
+
+        csv
+    }
+}
+
+/// Layout of the table written by `to_csv_with`, the default one is the `to_csv` one
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Between columns, `,` by default
+    pub delimiter: char,
+    /// Whether acceptance is written in an `Accept` column as `true` or `false` instead of a
+    /// `*` before the state
+    pub accept_column: bool,
+    /// Whether states are written as `<1>` rather than `1`
+    pub bracket_states: bool
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions { delimiter: ',', accept_column: false, bracket_states: true }
+    }
+}
+
+/// The transition table, as written by `to_csv`
+impl<T: Display + Debug + Eq + Hash + Ord> Display for Dfa<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_csv())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CsvError {
+    /// The first line is not like `State,a,b`, with a single char per symbol
+    InvalidHeader,
+    /// A row without one cell per symbol, besides the state one
+    InvalidRow { line: usize },
+    /// A cell that is neither `-` nor a list of states like `<1><2>`
+    InvalidCell { line: usize, col: usize },
+    /// A transition to a state without its own row
+    UnknownState { line: usize, state: usize },
+    /// No state marked with `->`
+    MissingInitial
+}
+
+impl Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CsvError::InvalidHeader => write!(f, "invalid header, expected `State` and single-char symbols"),
+            CsvError::InvalidRow { line } => write!(f, "line {}: wrong number of cells", line),
+            CsvError::InvalidCell { line, col } => write!(f, "line {}, cell {}: invalid states", line, col),
+            CsvError::UnknownState { line, state } => write!(f, "line {}: unknown state <{}>", line, state),
+            CsvError::MissingInitial => write!(f, "no initial state marked with `->`")
+        }
+    }
+}
+
+impl Error for CsvError {}
+
+/// Parse a `to_csv` cell of states like `<1>` or `<1><2>`
+fn parse_csv_states(cell: &str) -> Option<Vec<usize>> {
+    let mut states = Vec::new();
+    let mut rest = cell;
+
+    while !rest.is_empty() {
+        if !rest.starts_with('<') { return None; }
+
+        let end = rest.find('>')?;
+        states.push(rest[1..end].parse().ok()?);
+        rest = &rest[end + 1..];
+    }
+
+    if states.is_empty() { None } else { Some(states) }
+}
+
+impl Dfa<char> {
+    /// Import a transition table written by `to_csv`. Epsilon transitions are not part of the
+    /// table, and symbols can't be `,` since it separates the cells
+    #[allow(dead_code)]
+    pub fn from_csv(s: &str) -> Result<Dfa<char>, CsvError> {
+        let mut lines = s.lines().enumerate().filter(|&(_, l)| !l.trim().is_empty());
+        let mut dfa = Dfa::new();
+        let mut initial = None;
+        let mut rows = Vec::new();
+
+        let alphabet: Vec<char> = match lines.next() {
+            Some((_, header)) => {
+                let mut cells = header.split(',');
+
+                if cells.next() != Some("State") {
+                    return Err(CsvError::InvalidHeader);
+                }
+
+                // Control chars are escaped by `to_csv`
+                cells
+                    .map(|c| unescape_control(c).ok_or(CsvError::InvalidHeader))
+                    .collect::<Result<_, _>>()?
+            },
+            None => return Err(CsvError::InvalidHeader)
+        };
+
+        dfa.states.clear();
+
+        // All states are read first, so transitions may be checked against them
+        for (n, l) in lines {
+            let line = n + 1;
+            let cells: Vec<&str> = l.split(',').collect();
+
+            if cells.len() != alphabet.len() + 1 {
+                return Err(CsvError::InvalidRow { line });
+            }
+
+            let mut state = cells[0];
+            let is_initial = state.starts_with("->");
+
+            if is_initial { state = &state[2..]; }
+
+            let accept = state.starts_with('*');
+
+            if accept { state = &state[1..]; }
+
+            let index = match parse_csv_states(state) {
+                Some(ref states) if states.len() == 1 => states[0],
+                _ => return Err(CsvError::InvalidCell { line, col: 1 })
+            };
+
+            if is_initial { initial = Some(index); }
+
+            dfa.states.insert(index, accept);
+            rows.push((line, index, cells));
+        }
+
+        dfa.initial = initial.ok_or(CsvError::MissingInitial)?;
+        dfa.current = dfa.initial;
+
+        for c in &alphabet {
+            dfa.alphabet.insert(*c);
+        }
+
+        for (line, from, cells) in rows {
+            for (i, (cell, by)) in cells[1..].iter().zip(&alphabet).enumerate() {
+                if *cell == "-" { continue; }
+
+                let targets = parse_csv_states(cell).ok_or(CsvError::InvalidCell { line, col: i + 2 })?;
+
+                for to in targets {
+                    if !dfa.states.contains_key(&to) {
+                        return Err(CsvError::UnknownState { line, state: to });
+                    }
+
+                    dfa.create_transition_between(&from, &to, *by);
+                }
+            }
+        }
+
+        Ok(dfa)
+    }
+
+    /// Same as `accepts`, but takes the input as a string
+    #[allow(dead_code)]
+    pub fn accepts_str(&self, s: &str) -> bool {
+        let input: Vec<char> = s.chars().collect();
+
+        self.accepts(&input)
+    }
+
+    /// Copy of the DFA ignoring the case of ASCII letters: every transition by one gets a twin
+    /// by the other case to the same state, then it is determinized. Other chars are untouched
+    #[allow(dead_code)]
+    pub fn to_case_insensitive(&self) -> Dfa<char> {
+        let mut dfa = self.clone();
+        let twins: Vec<(usize, char, usize)> = self.iter_transitions()
+            .filter(|t| t.1.is_ascii_alphabetic())
+            .map(|(from, c, to)| {
+                let other = if c.is_ascii_lowercase() { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() };
+
+                (from, other, to)
+            })
+            .collect();
+
+        for (from, c, to) in twins {
+            dfa.create_transition_between(&from, &to, c);
+        }
+
+        dfa.determinize();
+
+        dfa
+    }
+
+    /// Split `input` in tokens by longest match: from each position the DFA runs as far as it
+    /// can, and the longest prefix ending in an accepting state is a token, with its position
+    /// and the state it ended in (see `state_label` for its name). Fails at the first char that
+    /// starts no token
+    #[allow(dead_code)]
+    pub fn tokenize(&self, input: &str) -> Result<Vec<Token>, LexError> {
+        self.tokenize_reader(input.as_bytes())
+            .map(|token| token.expect("A str is read whole and is UTF-8"))
+            .collect()
+    }
+
+    /// Export the DFA as JSON, everything sorted so the output is stable:
+    /// `{"initial": 0, "states": [[0, false], [1, true]], "alphabet": ["a"],
+    /// "transitions": [[0, "a", 1]], "epsilon": [[1, 0]], "labels": [[1, "A"]],
+    /// "priorities": [[1, 2]]}`
+    #[allow(dead_code)]
+    pub fn to_json(&self) -> String {
+        let states: Vec<(&usize, &State)> = self.states.iter().collect();
+        let alphabet: Vec<&char> = self.alphabet.iter().collect();
+        let mut transitions: Vec<(usize, char, usize)> = Vec::new();
+        let mut epsilon: Vec<(usize, usize)> = Vec::new();
+
+        for (from, ts) in &self.transitions {
+            for t in ts {
+                transitions.push((*from, t.0, t.1));
+            }
+        }
+
+        for (from, es) in &self.epsilon {
+            for to in es {
+                epsilon.push((*from, *to));
+            }
+        }
+
+        let states: Vec<String> = states
+            .iter()
+            .map(|&(s, accept)| format!("[{}, {}]", s, accept))
+            .collect();
+        let alphabet: Vec<String> = alphabet
+            .iter()
+            .map(|c| json::quote(&c.to_string()))
+            .collect();
+        let transitions: Vec<String> = transitions
+            .iter()
+            .map(|&(from, c, to)| format!("[{}, {}, {}]", from, json::quote(&c.to_string()), to))
+            .collect();
+        let epsilon: Vec<String> = epsilon
+            .iter()
+            .map(|&(from, to)| format!("[{}, {}]", from, to))
+            .collect();
+        let labels: Vec<String> = self.labels
+            .iter()
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .map(|(s, label)| format!("[{}, {}]", s, json::quote(label)))
+            .collect();
+        let priorities: Vec<String> = self.priorities
+            .iter()
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .map(|(s, p)| format!("[{}, {}]", s, p))
+            .collect();
+
+        format!(
+            "{{\"initial\": {}, \"states\": [{}], \"alphabet\": [{}], \"transitions\": [{}], \"epsilon\": [{}], \"labels\": [{}], \"priorities\": [{}]}}",
+            self.initial,
+            states.join(", "),
+            alphabet.join(", "),
+            transitions.join(", "),
+            epsilon.join(", "),
+            labels.join(", "),
+            priorities.join(", ")
+        )
+    }
+
+    /// Import a DFA exported by `to_json`. `alphabet`, `epsilon`, `labels` and `priorities` may
+    /// be left out
+    #[allow(dead_code)]
+    pub fn from_json(s: &str) -> Result<Dfa<char>, JsonError> {
+        fn schema<V>(msg: &str) -> Result<V, JsonError> {
+            Err(JsonError::Schema(msg.to_string()))
+        }
+
+        fn list<'a>(value: &'a Value, key: &str, required: bool) -> Result<&'a [Value], JsonError> {
+            match value.get(key) {
+                Some(Value::Array(items)) => Ok(items),
+                None if !required => Ok(&[]),
+                _ => schema(&format!("`{}` must be an array", key))
+            }
+        }
+
+        fn index(value: &Value) -> Result<usize, JsonError> {
+            match *value {
+                Value::Number(n) if n >= 0 => Ok(n as usize),
+                _ => schema("states must be non-negative integers")
+            }
+        }
+
+        fn symbol(value: &Value) -> Result<char, JsonError> {
+            if let Value::String(ref s) = *value {
+                let mut chars = s.chars();
+
+                if let (Some(c), None) = (chars.next(), chars.next()) {
+                    return Ok(c);
+                }
+            }
+
+            schema("symbols must be strings of a single char")
+        }
+
+        let value = json::parse(s)?;
+        let mut dfa = Dfa::new();
+
+        dfa.states.clear();
+
+        for state in list(&value, "states", true)? {
+            match *state {
+                Value::Array(ref pair) if pair.len() == 2 => {
+                    let accept = match pair[1] {
+                        Value::Bool(accept) => accept,
+                        _ => return schema("state acceptance must be a boolean")
+                    };
+
+                    dfa.states.insert(index(&pair[0])?, accept);
+                },
+                _ => return schema("states must be [index, accept] pairs")
+            }
+        }
+
+        dfa.initial = match value.get("initial") {
+            Some(initial) => index(initial)?,
+            None => return schema("`initial` is missing")
+        };
+        dfa.current = dfa.initial;
+
+        if !dfa.states.contains_key(&dfa.initial) {
+            return schema("the initial state is not listed in `states`");
+        }
+
+        for c in list(&value, "alphabet", false)? {
+            dfa.alphabet.insert(symbol(c)?);
+        }
+
+        for transition in list(&value, "transitions", true)? {
+            match *transition {
+                Value::Array(ref t) if t.len() == 3 => {
+                    let (from, by, to) = (index(&t[0])?, symbol(&t[1])?, index(&t[2])?);
+
+                    if !dfa.states.contains_key(&from) || !dfa.states.contains_key(&to) {
+                        return schema("transitions must be between listed states");
+                    }
+
+                    dfa.create_transition_between(&from, &to, by);
+                },
+                _ => return schema("transitions must be [from, symbol, to] triples")
+            }
+        }
+
+        for e in list(&value, "epsilon", false)? {
+            match *e {
+                Value::Array(ref pair) if pair.len() == 2 => {
+                    let (from, to) = (index(&pair[0])?, index(&pair[1])?);
+
+                    if !dfa.states.contains_key(&from) || !dfa.states.contains_key(&to) {
+                        return schema("epsilon transitions must be between listed states");
+                    }
+
+                    dfa.create_epsilon_transition_between(&from, &to);
+                },
+                _ => return schema("epsilon transitions must be [from, to] pairs")
+            }
+        }
+
+        for l in list(&value, "labels", false)? {
+            match *l {
+                Value::Array(ref pair) if pair.len() == 2 => {
+                    let state = index(&pair[0])?;
+
+                    if !dfa.states.contains_key(&state) {
+                        return schema("labels must be of listed states");
+                    }
+
+                    match pair[1] {
+                        Value::String(ref label) => { dfa.labels.insert(state, label.clone()); },
+                        _ => return schema("labels must be strings")
+                    }
+                },
+                _ => return schema("labels must be [state, label] pairs")
+            }
+        }
+
+        for p in list(&value, "priorities", false)? {
+            match *p {
+                Value::Array(ref pair) if pair.len() == 2 => {
+                    let state = index(&pair[0])?;
+
+                    if !dfa.states.contains_key(&state) {
+                        return schema("priorities must be of listed states");
+                    }
+
+                    match pair[1] {
+                        Value::Number(n) if n >= i32::MIN as i64 && n <= i32::MAX as i64 => {
+                            dfa.priorities.insert(state, n as i32);
+                        },
+                        _ => return schema("priorities must be 32-bit integers")
+                    }
+                },
+                _ => return schema("priorities must be [state, priority] pairs")
+            }
+        }
+
+        Ok(dfa)
+    }
+
+    /// Same as `shortest_accepted`, but returns the input as a string
+    #[allow(dead_code)]
+    pub fn shortest_accepted_string(&self) -> Option<String> {
+        self.shortest_accepted().map(|word| word.into_iter().collect())
+    }
+
+    /// Same as `coverage_strings`, but returns the inputs as strings
+    #[allow(dead_code)]
+    pub fn coverage_strings_str(&self) -> Vec<String> {
+        self.coverage_strings().into_iter().map(|word| word.into_iter().collect()).collect()
+    }
+}
+
+impl Dfa<String> {
+    /// Copy of the DFA keyed on small ids instead of the symbol names, which are cheaper to
+    /// compare and clone in `determinize` and friends. Ids follow the alphabet order, so the
+    /// exports are the same as the original's, and `SymbolTable::resolve` gives it back
+    #[allow(dead_code)]
+    pub fn intern_symbols(&self) -> (Dfa<Symbol>, Rc<SymbolTable>) {
+        let mut table = SymbolTable::new();
+
+        for symbol in &self.alphabet {
+            table.intern(symbol);
+        }
+
+        let table = Rc::new(table);
+        let interned = self.map_symbols(|symbol| Symbol { id: table.ids[symbol], table: table.clone() });
+
+        (interned, table)
+    }
+}
+
+impl Dfa<Symbol> {
+    /// The symbol of the alphabet named `name`, e.g. to build an input to run
+    #[allow(dead_code)]
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.alphabet.iter().find(|symbol| symbol.name() == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::io;
+
+    /// `(ab)*` with a state more than needed for each symbol
+    fn redundant_ab_star() -> Dfa<char> {
+        Dfa::from_transitions(0, &[0, 2], &[(0, 'a', 1), (1, 'b', 2), (2, 'a', 3), (3, 'b', 0)])
+    }
+
+    /// Automaton accepting only `w`
+    fn word(w: &str) -> Dfa<char> {
+        let mut dfa = Dfa::new();
+
+        for c in w.chars() {
+            let next = dfa.add_state(false);
+
+            dfa.create_transition_and_walk(c, next);
+        }
+
+        dfa.set_current_state_accept(true);
+        dfa.rewind();
+
+        dfa
+    }
+
+    /// `tests/ndetgrammar.in`, with states going to several others by the same symbol
+    fn multi_target() -> Dfa<char> {
+        ::grammar::parse_grammar(include_str!("../tests/ndetgrammar.in").as_bytes()).unwrap()
+    }
+
+    /// Parse, determinize, minimize and complete `grammar` as the CLI does
+    fn compiled(grammar: &str) -> Dfa<char> {
+        let mut dfa = ::grammar::parse_grammar(grammar.as_bytes()).unwrap();
+
+        dfa.determinize();
+        dfa.minimize();
+        dfa.insert_error_state();
+
+        dfa
+    }
+
+    fn hash_of(dfa: &Dfa<char>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        dfa.hash(&mut hasher);
+
+        hasher.finish()
+    }
+
+    /// `a` then a newline, through the same steps as the CLI
+    fn newline_dfa() -> Dfa<char> {
+        let mut dfa = ::grammar::parse_grammar("<S> ::= a<A>\n<A> ::= \\n\n".as_bytes()).unwrap();
+
+        dfa.determinize();
+        dfa.minimize();
+        dfa.insert_error_state();
+
+        dfa
+    }
+
+    #[test]
+    fn accepts_empty_input_if_initial_accepts() {
+        let star = redundant_ab_star();
+        let plus = Dfa::from_transitions(0, &[2], &[(0, 'a', 1), (1, 'b', 2), (2, 'a', 1)]);
+
+        assert!(star.accepts(&[]));
+        assert!(!plus.accepts(&[]));
+        assert!(plus.accepts(&['a', 'b', 'a', 'b']));
+        assert!(!plus.accepts(&['a', 'b', 'b']));
+    }
+
+    #[test]
+    fn walk_stops_at_dead_end() {
+        let mut dfa = Dfa::from_transitions(0, &[2], &[(0, 'a', 1), (1, 'b', 2)]);
+
+        assert_eq!(dfa.walk(&'a'), Ok(1));
+        assert_eq!(dfa.walk(&'b'), Ok(2));
+        assert!(dfa.walk(&'a').is_err());
+        assert_eq!(dfa.current(), 2);
+    }
+
+    #[test]
+    fn hopcroft_reduces_equivalent_automata_alike() {
+        let mut redundant = redundant_ab_star();
+        let mut star = Dfa::from_transitions(0, &[0], &[(0, 'a', 1), (1, 'b', 0)]);
+
+        redundant.insert_error_state();
+        star.insert_error_state();
+        redundant.minimize_hopcroft();
+        star.minimize_hopcroft();
+
+        assert_eq!(redundant.states().len(), star.states().len());
+        assert_eq!(redundant.states().len(), 3);
+    }
+
+    #[test]
+    fn error_state_is_a_non_accepting_trap() {
+        let mut dfa = Dfa::from_transitions(0, &[1], &[(0, 'a', 1), (1, 'b', 0)]);
+
+        dfa.insert_error_state();
+
+        let traps = dfa.trap_states();
+
+        assert_eq!(traps.len(), 1);
+        assert!(!dfa.state_accept(traps[0]));
+        assert!(dfa.alphabet().iter().all(|c| dfa.transition(traps[0], c) == Some(traps[0])));
+        assert!(dfa.is_complete());
+    }
+
+    #[test]
+    fn dot_node_ids_are_plain_indexes() {
+        let mut dfa = multi_target();
+
+        dfa.determinize();
+
+        let dot = dfa.to_dot();
+
+        assert!(dot.lines().skip(1).all(|l| !l.split('[').next().unwrap().contains('{')));
+    }
+
+    #[test]
+    fn remove_state_drops_transitions_into_it() {
+        let mut dfa = Dfa::from_transitions(0, &[2], &[(0, 'a', 1), (0, 'c', 1), (1, 'b', 2), (0, 'b', 2)]);
+
+        assert!(dfa.remove_state(1).is_some());
+        assert!(dfa.transitions().values().flatten().all(|t| t.1 != 1));
+        assert_eq!(dfa.transitions().values().flatten().count(), 1);
+    }
+
+    #[test]
+    fn set_current_needs_an_existing_state() {
+        let mut dfa: Dfa<char> = Dfa::new();
+
+        dfa.add_state(false);
+        dfa.add_state(true);
+
+        assert!(dfa.set_current(2).is_ok());
+        assert!(dfa.set_current(3).is_err());
+
+        dfa.remove_state(1);
+
+        assert!(dfa.set_current(1).is_err());
+        assert_eq!(dfa.current(), 2);
+    }
+
+    #[test]
+    fn add_state_after_removal_does_not_collide() {
+        let mut dfa: Dfa<char> = Dfa::new();
+
+        for _ in 0..3 {
+            dfa.add_state(false);
+        }
+
+        dfa.remove_state(2);
+
+        let before: Vec<usize> = dfa.states().keys().cloned().collect();
+        let added = dfa.add_state(true);
+
+        assert!(!before.contains(&added));
+        assert_eq!(dfa.states().len(), 4);
+    }
+
+    #[test]
+    fn epsilon_closure_follows_chains() {
+        let mut dfa = Dfa::from_transitions(0, &[3], &[(2, 'a', 3)]);
+
+        dfa.create_epsilon_transition_between(&0, &1);
+        dfa.create_epsilon_transition_between(&1, &2);
+
+        let closure = dfa.epsilon_closure(&[0].iter().cloned().collect());
+
+        assert_eq!(closure, [0, 1, 2].iter().cloned().collect());
+
+        dfa.determinize();
+
+        assert!(dfa.accepts_str("a"));
+    }
+
+    #[test]
+    fn complement_swaps_acceptance() {
+        let mut dfa = word("ab");
+
+        dfa.complement();
+
+        assert!(!dfa.accepts_str("ab"));
+        assert!(dfa.accepts_str(""));
+        assert!(dfa.accepts_str("a"));
+        assert!(dfa.accepts_str("abb"));
+    }
+
+    #[test]
+    fn intersection_of_even_length_and_only_a() {
+        let even = Dfa::from_transitions(0, &[0], &[(0, 'a', 1), (0, 'b', 1), (1, 'a', 0), (1, 'b', 0)]);
+        let only_a = ::regex::from_regex("a*").unwrap();
+        let both = even.intersection(&only_a);
+
+        assert!(both.accepts_str(""));
+        assert!(both.accepts_str("aaaa"));
+        assert!(!both.accepts_str("aaa"));
+        assert!(!both.accepts_str("ab"));
+    }
+
+    #[test]
+    fn union_accepts_both_words() {
+        let dfa = word("if").union(&word("else"));
+
+        assert!(dfa.accepts_str("if"));
+        assert!(dfa.accepts_str("else"));
+        assert!(!dfa.accepts_str("ifelse"));
+        assert!(!dfa.accepts_str(""));
+    }
+
+    #[test]
+    fn emptiness() {
+        let unreachable = Dfa::from_transitions(0, &[2], &[(0, 'a', 1)]);
+
+        assert!(Dfa::<char>::new().is_empty());
+        assert!(unreachable.is_empty());
+        assert!(!::regex::from_regex("a").unwrap().is_empty());
+    }
+
+    #[test]
+    fn shortest_accepted_word() {
+        let dfa = word("abcde").union(&word("abc"));
+
+        assert_eq!(dfa.shortest_accepted(), Some(vec!['a', 'b', 'c']));
+        assert_eq!(Dfa::<char>::new().shortest_accepted(), None);
+    }
+
+    #[test]
+    fn words_up_to_bounds_cycles() {
+        let words = redundant_ab_star().words_up_to(4);
+
+        assert_eq!(words, vec![vec![], vec!['a', 'b'], vec!['a', 'b', 'a', 'b']]);
+    }
+
+    #[test]
+    fn determinize_makes_deterministic() {
+        let mut dfa = multi_target();
+
+        assert!(!dfa.is_deterministic());
+        assert!(dfa.determinize());
+        assert!(dfa.is_deterministic());
+        assert!(!dfa.determinize());
+    }
+
+    #[test]
+    fn clone_is_independent() {
+        let dfa = redundant_ab_star();
+        let mut clone = dfa.clone();
+
+        clone.add_state(true);
+
+        assert_eq!(dfa.states().len(), 4);
+        assert_eq!(clone.states().len(), 5);
+    }
+
+    #[test]
+    fn structural_equality() {
+        let dfa = redundant_ab_star();
+        let mut modified = dfa.clone();
+
+        assert!(dfa == dfa.clone());
+
+        modified.create_transition_between(&3, &3, 'a');
+
+        assert!(dfa != modified);
+    }
+
+    #[test]
+    fn language_equivalence() {
+        let star = Dfa::from_transitions(0, &[0], &[(0, 'a', 1), (1, 'b', 0)]);
+        let plus = Dfa::from_transitions(0, &[2], &[(0, 'a', 1), (1, 'b', 2), (2, 'a', 1)]);
+
+        assert!(redundant_ab_star().equivalent(&star));
+        assert!(!redundant_ab_star().equivalent(&plus));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json_round_trip() {
+        let dfa = multi_target();
+        let json = ::serde_json::to_string(&dfa).unwrap();
+        let back: Dfa<char> = ::serde_json::from_str(&json).unwrap();
+
+        assert!(back == dfa);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut dfa = Dfa::from_transitions(0, &[2], &[(0, '"', 1), (1, '\\', 2), (2, 'é', 0)]);
+
+        dfa.create_epsilon_transition_between(&2, &0);
+        dfa.add_state(false);
+
+        let back = Dfa::from_json(&dfa.to_json()).unwrap();
+
+        assert!(back == dfa);
+        assert_eq!(back.epsilon_transitions(), dfa.epsilon_transitions());
+        assert!(Dfa::from_json("{\"initial\": 0}").is_err());
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let dfa = compiled(include_str!("../tests/exemplo.in"));
+        let csv = dfa.to_csv();
+        let back = Dfa::from_csv(&csv).unwrap();
+
+        assert_eq!(back.to_csv(), csv);
+        assert!(back.accepts_str("senao"));
+        assert!(!back.accepts_str("sen"));
+    }
+
+    #[test]
+    fn csv_round_trip_of_escaped_control_chars() {
+        let dfa = Dfa::from_transitions(0, &[1], &[(0, '\u{7}', 1), (1, '\u{1b}', 0), (1, '\t', 1)]);
+        let csv = dfa.to_csv();
+        let back = Dfa::from_csv(&csv).unwrap();
+
+        assert_eq!(csv.lines().next(), Some("State,\\u{7},\\t,\\u{1b}"));
+        assert_eq!(back.to_csv(), csv);
+        assert!(back.accepts(&['\u{7}', '\t', '\u{1b}', '\u{7}']));
+    }
+
+    #[test]
+    fn mermaid_has_initial_arrow_and_edges() {
+        let mermaid = Dfa::from_transitions(0, &[1], &[(0, 'a', 1)]).to_mermaid();
+
+        assert!(mermaid.contains("[*] --> S0"));
+        assert!(mermaid.contains("S0 --> S1 : a"));
+    }
+
+    #[test]
+    fn tokenize_keywords() {
+        let dfa = ::regex::from_regex("if|else| ").unwrap();
+        let lexemes: Vec<String> = dfa.tokenize("ifelse if").unwrap().into_iter().map(|t| t.lexeme).collect();
+
+        assert_eq!(lexemes, vec!["if", "else", " ", "if"]);
+    }
+
+    #[test]
+    fn tokens_carry_their_label() {
+        let dfa = ::grammar::parse_grammar(include_str!("../tests/named-grammar.in").as_bytes()).unwrap();
+        let tokens = dfa.tokenize("aab").unwrap();
+        let labels: Vec<Option<&str>> = tokens.iter().map(|t| dfa.state_label(t.state)).collect();
+
+        assert_eq!(labels, vec![Some("IDENT"), Some("NUM")]);
+    }
+
+    #[test]
+    fn json_round_trip_keeps_labels() {
+        let grammar = "<S> ::= a<IDENT> | b<NUM>\n<IDENT> ::= a<IDENT> | <>\n<NUM> ::= b<NUM> | <>\n";
+        let dfa = ::grammar::parse_grammar(grammar.as_bytes()).unwrap();
+        let back = Dfa::from_json(&dfa.to_json()).unwrap();
+
+        assert!(back == dfa);
+        assert_eq!(back.state_label(1), Some("IDENT"));
+    }
+
+    #[test]
+    fn from_json_without_labels() {
+        let dfa = Dfa::from_json(r#"{"initial": 0, "states": [[0, true]], "transitions": []}"#).unwrap();
+
+        assert_eq!(dfa.state_label(0), None);
+    }
+
+    #[test]
+    fn priority_picks_keyword_over_identifier() {
+        // Identifiers over `i` and `f` in state 1, the `if` keyword in state 3
+        let mut dfa = Dfa::from_transitions(0, &[1, 3], &[
+            (0, 'i', 1), (0, 'f', 1), (1, 'i', 1), (1, 'f', 1), (0, 'i', 2), (2, 'f', 3)
+        ]);
+
+        dfa.set_state_label(1, "IDENT");
+        dfa.set_state_label(3, "IF");
+        dfa.set_priority(3, 1);
+        dfa.determinize();
+
+        let keyword = dfa.tokenize("if").unwrap();
+        let identifier = dfa.tokenize("fi").unwrap();
+
+        assert_eq!(keyword.len(), 1);
+        assert_eq!(dfa.state_label(keyword[0].state), Some("IF"));
+        assert_eq!(dfa.state_label(identifier[0].state), Some("IDENT"));
+    }
+
+    #[test]
+    fn json_round_trip_keeps_priorities() {
+        let mut dfa = Dfa::from_transitions(0, &[1, 2], &[(0, 'a', 1), (0, 'b', 2)]);
+
+        dfa.set_state_label(1, "KEYWORD");
+        dfa.set_priority(1, 3);
+        dfa.set_priority(2, -1);
+
+        let back = Dfa::from_json(&dfa.to_json()).unwrap();
+
+        assert!(back == dfa);
+        assert_eq!(back.priority(2), -1);
+    }
+
+    #[test]
+    fn tokenize_reader_across_buffer_refills() {
+        let dfa = ::regex::from_regex("if|iff| |\n").unwrap();
+        let reader = io::BufReader::with_capacity(2, io::Cursor::new("iff if\niff"));
+        let tokens: Vec<Token> = dfa.tokenize_reader(reader).map(|t| t.unwrap().unwrap()).collect();
+        let lexemes: Vec<&str> = tokens.iter().map(|t| t.lexeme.as_str()).collect();
+
+        assert_eq!(lexemes, vec!["iff", " ", "if", "\n", "iff"]);
+        assert_eq!((tokens[4].offset, tokens[4].line, tokens[4].col), (7, 2, 1));
+    }
+
+    #[test]
+    fn tokenize_stops_at_the_trap_of_a_complete_dfa() {
+        let mut dfa = ::regex::from_regex("if| ").unwrap();
+
+        dfa.complete();
+
+        assert_eq!(dfa.tokenize("if if").unwrap().len(), 3);
+
+        // The match ends at the trap, before the invalid byte is read
+        let reader = io::BufReader::with_capacity(1, &b"if \xff"[..]);
+        let first = dfa.tokenize_reader(reader).next().unwrap().unwrap().unwrap();
+
+        assert_eq!(first.lexeme, "if");
+    }
+
+    #[test]
+    fn run_trace_of_chain() {
+        let dfa = Dfa::from_transitions(0, &[2], &[(0, 'a', 1), (1, 'b', 2)]);
+
+        assert_eq!(dfa.run_trace(&['a', 'b']), (vec![0, 1, 2], true));
+        assert_eq!(dfa.run_trace(&['a']), (vec![0, 1], false));
+        assert_eq!(dfa.run_trace(&['b']), (vec![0], false));
+    }
+
+    #[test]
+    fn dead_states_of_diamond_and_trap() {
+        let diamond = Dfa::from_transitions(0, &[3], &[(0, 'a', 1), (0, 'b', 2), (1, 'c', 3), (2, 'c', 3)]);
+        let half_dead = Dfa::from_transitions(0, &[3], &[(0, 'a', 1), (0, 'b', 2), (1, 'c', 3), (2, 'c', 4)]);
+        let trap = Dfa::from_transitions(0, &[1], &[(0, 'a', 1), (0, 'b', 2), (2, 'a', 2), (2, 'b', 2)]);
+
+        assert!(diamond.get_dead_states().is_empty());
+        assert_eq!(half_dead.get_dead_states(), vec![2, 4]);
+        assert_eq!(trap.get_dead_states(), vec![2]);
+    }
+
+    #[test]
+    fn ndt_of_state_without_transitions() {
+        let mut dfa: Dfa<char> = Dfa::new();
+        let state = dfa.add_state(false);
+
+        assert!(dfa.ndt_of(&state).is_empty());
+        assert!(dfa.ndt_of(&42).is_empty());
+    }
+
+    #[test]
+    fn dot_edges_go_to_real_states() {
+        let mut dfa = ::grammar::parse_grammar(include_str!("../tests/exemplo_artigo.in").as_bytes()).unwrap();
+
+        dfa.determinize();
+
+        let dot = dfa.to_dot();
+
+        assert!(dot.contains(&format!("__start -> {};", dfa.initial())));
+
+        for line in dot.lines().filter(|l| l.contains(" -> ") && !l.starts_with("__start")) {
+            let to: usize = line.split(" -> ").nth(1).unwrap().split(' ').next().unwrap().parse().unwrap();
+
+            assert!(dfa.states().contains_key(&to), "{}", line);
+        }
+    }
+
+    #[test]
+    fn reverse_accepts_reversed_words() {
+        let mut reversed = ::regex::from_regex("abc").unwrap().reverse();
+
+        reversed.determinize();
+
+        assert!(reversed.accepts_str("cba"));
+        assert!(!reversed.accepts_str("abc"));
+    }
+
+    #[test]
+    fn brzozowski_gives_minimal_automaton() {
+        for pattern in &["(ab)*", "a*b|b*a", "(a|b)*abb", "x?y?z?", "()", "a(b|c)*d|e", "(a|b)*"] {
+            let dfa = ::regex::from_regex(pattern).unwrap();
+            let mut brzozowski = dfa.clone();
+            let mut minimal = dfa.minimal();
+
+            brzozowski.minimize_brzozowski();
+            minimal.remove_trap_states();
+
+            assert!(brzozowski.equivalent(&dfa), "{}", pattern);
+            assert_eq!(brzozowski.states().len(), minimal.states().len(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn concat_accepts_only_the_concatenation() {
+        let mut dfa = ::regex::from_regex("ab").unwrap().concat(&::regex::from_regex("cd").unwrap());
+
+        dfa.determinize();
+
+        assert!(dfa.accepts_str("abcd"));
+        assert_eq!(dfa.words_up_to(6), vec![vec!['a', 'b', 'c', 'd']]);
+    }
+
+    #[test]
+    fn kleene_star_of_word() {
+        let mut dfa = ::regex::from_regex("ab").unwrap();
+
+        dfa.kleene_star();
+        dfa.determinize();
+
+        assert!(dfa.accepts_str(""));
+        assert!(dfa.accepts_str("ab"));
+        assert!(dfa.accepts_str("abab"));
+        assert!(!dfa.accepts_str("aba"));
+    }
+
+    #[test]
+    fn normalize_after_minimize() {
+        // From 3, minimizing leaves 0 and 3: 1, 2 and 5 are unreachable and 4 is dead
+        let mut dfa = Dfa::from_transitions(0, &[0], &[(3, 'a', 0), (0, 'a', 3), (3, 'b', 4), (5, 'a', 0)]);
+
+        dfa.set_initial(3);
+        dfa.rewind();
+
+        let original = dfa.clone();
+
+        dfa.minimize();
+        dfa.normalize();
+
+        assert!(dfa.states().keys().cloned().eq(0..dfa.states().len()));
+        assert!(dfa.equivalent(&original));
+        assert!(dfa.state_accept(1));
+    }
+
+    #[test]
+    fn csv_is_deterministic() {
+        let grammar = include_str!("../tests/multi-grammar.in");
+
+        assert_eq!(compiled(grammar).to_csv(), compiled(grammar).to_csv());
+    }
+
+    #[test]
+    fn minimal_leaves_self_alone() {
+        let dfa = redundant_ab_star();
+        let minimal = dfa.minimal();
+
+        assert_eq!(dfa.states().len(), 4);
+        assert!(minimal.states().len() < 4);
+        assert!(minimal.equivalent(&dfa));
+    }
+
+    #[test]
+    fn minimal_of_empty_language_is_a_single_state() {
+        let minimal = Dfa::<char>::new().minimal();
+
+        assert_eq!(minimal.num_states(), 1);
+        assert!(!minimal.accepts(&[]));
+        assert!(!minimal.accepts(&['a']));
+    }
+
+    #[test]
+    fn minimal_of_dead_automaton_accepts_nothing() {
+        let dfa = Dfa::from_transitions(3, &[], &[(3, 'a', 4), (4, 'b', 3)]);
+        let minimal = dfa.minimal();
+
+        assert_eq!(minimal.num_states(), 1);
+        assert_eq!(*minimal.initial(), 3);
+        assert!(!minimal.accepts(&['a', 'b']));
+    }
+
+    #[test]
+    fn builder_for_ab_star() {
+        let dfa = ::builder::DfaBuilder::new().accept("S").edge("S", 'a', "A").edge("A", 'b', "S").build();
+
+        assert!(dfa.accepts_str("abab"));
+        assert!(!dfa.accepts_str("aba"));
+    }
+
+    #[test]
+    fn iter_transitions_counts_each_destination() {
+        let dfa = multi_target();
+
+        assert_eq!(dfa.iter_transitions().count(), 10);
+        assert_eq!(dfa.iter_transitions().filter(|&(from, by, _)| from == 0 && *by == 'a').count(), 2);
+    }
+
+    #[test]
+    fn transition_lookup() {
+        let dfa = redundant_ab_star();
+
+        assert_eq!(dfa.transition(1, &'b'), Some(2));
+        assert_eq!(dfa.transition(1, &'a'), None);
+        assert_eq!(dfa.transition(42, &'a'), None);
+    }
+
+    #[test]
+    fn remove_transition_makes_deterministic() {
+        let mut dfa = Dfa::from_transitions(0, &[1, 2], &[(0, 'a', 1), (0, 'a', 2)]);
+
+        assert!(!dfa.is_deterministic());
+        assert!(dfa.remove_transition(0, &'a', 2));
+        assert!(!dfa.remove_transition(0, &'a', 2));
+        assert!(dfa.is_deterministic());
+    }
+
+    #[test]
+    fn missing_transitions_of_incomplete_dfa() {
+        let dfa = Dfa::from_transitions(0, &[1], &[(0, 'a', 1), (1, 'b', 0), (1, 'c', 1)]);
+        let gaps = dfa.missing_transitions();
+
+        assert!(!dfa.is_complete());
+        assert_eq!(gaps.len(), 2);
+        assert_eq!(gaps[&0], ['b', 'c'].iter().cloned().collect());
+        assert_eq!(gaps[&1], ['a'].iter().cloned().collect());
+    }
+
+    #[test]
+    fn accepting_and_non_accepting_states() {
+        let dfa = Dfa::from_transitions(0, &[2, 1], &[(0, 'a', 1), (0, 'b', 2), (2, 'c', 3)]);
+
+        assert_eq!(dfa.accepting_states(), vec![1, 2]);
+        assert_eq!(dfa.non_accepting_states(), vec![0, 3]);
+    }
+
+    #[test]
+    fn unreachable_states() {
+        let orphan = Dfa::from_transitions(0, &[1], &[(0, 'a', 1), (2, 'b', 1), (3, 'a', 2)]);
+        let mut epsilon = Dfa::from_transitions(0, &[2], &[(1, 'a', 2)]);
+
+        epsilon.create_epsilon_transition_between(&0, &1);
+
+        assert_eq!(orphan.get_unreachable_states(), vec![2, 3]);
+        assert_eq!(orphan.reachable_states(), [0, 1].iter().cloned().collect());
+        assert!(epsilon.get_unreachable_states().is_empty());
+    }
+
+    #[test]
+    fn transition_table() {
+        let (alphabet, table, accept) = Dfa::from_transitions(0, &[1], &[(0, 'a', 1), (1, 'b', 0), (1, 'c', 2)]).to_transition_table();
+
+        assert_eq!(alphabet, vec!['a', 'b', 'c']);
+        assert_eq!(table.len(), 3);
+        assert!(table.iter().all(|row| row.len() == 3));
+        assert_eq!(table[0], vec![Some(1), None, None]);
+        assert_eq!(table[1][2], Some(2));
+        assert_eq!(accept, vec![false, true, false]);
+    }
+
+    #[test]
+    fn csv_escapes_newline_symbol() {
+        let dfa = newline_dfa();
+        let csv = dfa.to_csv();
+
+        assert_eq!(csv.lines().next(), Some("State,\\n,a"));
+        assert_eq!(csv.lines().count(), dfa.num_states() + 1);
+        assert_eq!(Dfa::from_csv(&csv).unwrap().to_csv(), csv);
+    }
+
+    #[test]
+    fn dot_escapes_newline_symbol() {
+        let dot = newline_dfa().to_dot();
+
+        assert!(dot.contains("[label=\"\\\\n\"]"));
+        assert!(dot.lines().all(|l| l.ends_with(';') || l.ends_with('{') || l == "}"));
+    }
+
+    #[test]
+    fn mermaid_escapes_newline_symbol() {
+        let mermaid = newline_dfa().to_mermaid();
+
+        assert!(mermaid.contains(" : \\n"));
+        assert!(mermaid.lines().skip(1).all(|l| l.starts_with("    S") || l.starts_with("    [*]")));
+    }
+
+    #[test]
+    fn json_keeps_newline_symbol() {
+        let dfa = newline_dfa();
+
+        assert!(Dfa::from_json(&dfa.to_json()).unwrap() == dfa);
+        assert!(dfa.accepts_str("a\n"));
+    }
+
+    #[test]
+    fn language_size_of_finite_and_infinite() {
+        assert_eq!(::regex::from_regex("abc").unwrap().language_size(), Some(1));
+        assert_eq!(::regex::from_regex("a|b|cd?").unwrap().language_size(), Some(4));
+        assert_eq!(redundant_ab_star().language_size(), None);
+    }
+
+    #[test]
+    fn coverage_reaches_every_state() {
+        let dfa = compiled(include_str!("../tests/exemplo.in"));
+        let strings = dfa.coverage_strings();
+
+        assert_eq!(strings.len(), dfa.reachable_states().len());
+
+        let reached: BTreeSet<usize> = strings.iter().map(|s| *dfa.run_trace(s).0.last().unwrap()).collect();
+
+        assert_eq!(reached, dfa.reachable_states());
+    }
+
+    #[test]
+    fn difference_removes_a_word() {
+        let all = ::regex::from_regex("(a|b)*").unwrap();
+        let dfa = all.difference(&::regex::from_regex("ab").unwrap());
+        let words = dfa.words_up_to(3);
+
+        assert!(!dfa.accepts_str("ab"));
+        assert!(!words.contains(&vec!['a', 'b']));
+        assert_eq!(words.len(), all.words_up_to(3).len() - 1);
+    }
+
+    #[test]
+    fn subset() {
+        let a = ::regex::from_regex("a").unwrap();
+        let a_plus = ::regex::from_regex("a+").unwrap();
+
+        assert!(a.is_subset(&a_plus));
+        assert!(!a_plus.is_subset(&a));
+    }
+
+    #[test]
+    fn stats_of_multi_target() {
+        let dfa = multi_target();
+        let stats = dfa.stats();
+
+        assert_eq!(stats.num_states, dfa.states().len());
+        assert_eq!(stats.num_transitions, 10);
+        assert_eq!(stats.num_epsilon_transitions, 0);
+        assert_eq!(stats.alphabet_size, 2);
+        assert!(!stats.deterministic);
+        assert_eq!(dfa.out_degree(0), 3);
+        assert_eq!(dfa.in_degree(0), 2);
+    }
+
+    #[test]
+    fn determinize_large_nfa() {
+        // Any input of at least 100 symbols, guessing where the last 100 start
+        let mut edges = vec![(0, 'a', 0), (0, 'b', 0)];
+
+        for i in 0..100 {
+            edges.push((i, 'a', i + 1));
+            edges.push((i, 'b', i + 1));
+        }
+
+        let mut dfa = Dfa::from_transitions(0, &[100], &edges);
+        let start = ::std::time::Instant::now();
+
+        dfa.determinize();
+
+        assert!(start.elapsed() < ::std::time::Duration::from_secs(5));
+        assert_eq!(dfa.states().len(), 101);
+        assert!(dfa.accepts(&['a'; 100]));
+        assert!(!dfa.accepts(&['b'; 99]));
+    }
+
+    #[test]
+    fn dead_grammar_states() {
+        // `<B>` and `<D>` never reach an accepting state
+        let dfa = ::grammar::parse_grammar(include_str!("../tests/dead.in").as_bytes()).unwrap();
+
+        assert_eq!(dfa.get_dead_states(), vec![2, 4]);
+        assert!(dfa.get_unreachable_states().is_empty());
+    }
+
+    #[test]
+    fn explicit_trap() {
+        let dfa = Dfa::from_transitions(0, &[1], &[(0, 'a', 1), (0, 'b', 2), (2, 'a', 2), (2, 'b', 2), (1, 'a', 3)]);
+
+        assert_eq!(dfa.trap_states(), vec![2, 3]);
+    }
+
+    #[test]
+    fn display_is_the_csv() {
+        let dfa = Dfa::from_transitions(0, &[1], &[(0, 'a', 1), (1, 'b', 0)]);
+
+        assert!(format!("{}", dfa).starts_with("State,a,b\n"));
+        assert!(format!("{:?}", dfa).contains("initial"));
+    }
+
+    #[test]
+    fn map_symbols_to_lowercase() {
+        let mut dfa: Dfa<char> = Dfa::new();
+        let accept = dfa.add_state(true);
+
+        for c in (b'A'..b'Z' + 1).map(char::from).chain("az".chars()) {
+            dfa.create_transition_between(&0, &accept, c);
+        }
+
+        let lower = dfa.map_symbols(|c| c.to_ascii_lowercase());
+
+        assert_eq!(dfa.alphabet().len(), 28);
+        assert_eq!(lower.alphabet().len(), 26);
+        assert!(lower.accepts_str("q"));
+    }
+
+    #[test]
+    fn case_insensitive_keyword() {
+        let dfa = ::regex::from_regex("if").unwrap().to_case_insensitive();
+
+        for word in &["if", "IF", "If", "iF"] {
+            assert!(dfa.accepts_str(word), "{}", word);
+        }
+
+        assert!(!dfa.accepts_str("IFF"));
+    }
+
+    #[test]
+    fn project_removes_symbol() {
+        let mut dfa = Dfa::from_transitions(0, &[1], &[(0, 'a', 1), (0, 'b', 1), (1, 'b', 0)]);
+
+        dfa.project(&'b');
+
+        assert!(!dfa.alphabet().contains(&'b'));
+        assert!(dfa.iter_transitions().all(|(_, by, _)| *by != 'b'));
+        assert!(dfa.accepts_str("a"));
+    }
+
+    #[test]
+    fn csv_with_semicolons_and_accept_column() {
+        let dfa = Dfa::from_transitions(0, &[1], &[(0, 'a', 1), (1, 'b', 0)]);
+        let opts = CsvOptions { delimiter: ';', accept_column: true, ..CsvOptions::default() };
+
+        assert_eq!(dfa.to_csv_with(opts), "State;Accept;a;b\n-><0>;false;<1>;-\n<1>;true;-;<0>\n");
+    }
+
+    #[test]
+    fn complete_returns_non_accepting_trap() {
+        let mut dfa = Dfa::from_transitions(0, &[1], &[(0, 'a', 1), (1, 'b', 1)]);
+        let trap = dfa.complete();
+
+        assert!(!dfa.state_accept(trap));
+        assert!(dfa.is_complete());
+        assert_eq!(dfa.transition(0, &'b'), Some(trap));
+        assert_eq!(dfa.complete(), trap);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn samples_are_accepted() {
+        use rand::SeedableRng;
+
+        let dfa = redundant_ab_star();
+        let mut rng = ::rand::rngs::StdRng::seed_from_u64(7);
+
+        for _ in 0..50 {
+            let sample = dfa.sample(&mut rng, 10).unwrap();
+
+            assert!(dfa.accepts(&sample), "{:?}", sample);
+        }
+
+        assert_eq!(::regex::from_regex("aaa").unwrap().sample(&mut rng, 2), None);
+    }
+
+    #[test]
+    fn from_transitions_matches_built_dfa() {
+        let mut built: Dfa<char> = Dfa::new();
+        let (a, b) = (built.add_state(false), built.add_state(true));
+
+        built.create_transition_between(&0, &a, 'x');
+        built.create_transition_between(&0, &b, 'x');
+        built.create_transition_between(&a, &b, 'y');
+        built.create_transition_between(&b, &0, 'x');
+
+        assert!(Dfa::from_transitions(0, &[b], &[(0, 'x', a), (0, 'x', b), (a, 'y', b), (b, 'x', 0)]) == built);
+    }
+
+    #[test]
+    fn nondeterminism_report_lists_destinations() {
+        let dfa = multi_target();
+        let report = dfa.nondeterminism_report();
+        let from_initial: Vec<&(usize, char, Vec<usize>)> = report.iter().filter(|r| r.0 == *dfa.initial()).collect();
+
+        assert_eq!(from_initial.len(), 1);
+        assert_eq!(from_initial[0].1, 'a');
+        assert_eq!(from_initial[0].2.len(), 2);
+        assert_eq!(from_initial[0].2, dfa.transitions_from(0).filter(|t| *t.0 == 'a').map(|t| t.1).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn equivalent_accepting_states_merge() {
+        let mut dfa = Dfa::from_transitions(0, &[1, 2], &[(0, 'a', 1), (0, 'b', 2)]);
+
+        dfa.insert_error_state();
+
+        let merged = dfa.merge_equivalent_states();
+
+        assert_eq!(merged[&2], merged[&1]);
+        assert_eq!(dfa.states().len(), 3);
+        assert!(dfa.accepts_str("a") && dfa.accepts_str("b"));
+    }
+
+    #[test]
+    fn merge_equivalent_states_without_initial_rewinds() {
+        let mut dfa = Dfa::<char>::new();
+
+        dfa.minimize();
+
+        assert!(dfa.merge_equivalent_states().is_empty());
+        assert_eq!(dfa.current(), *dfa.initial());
+    }
+
+    #[test]
+    fn equivalence_classes_count_minimal_states() {
+        // Even length over `a` and `b`, counted up to 4
+        let dfa = Dfa::from_transitions(0, &[0, 2], &[
+            (0, 'a', 1), (0, 'b', 1), (1, 'a', 2), (1, 'b', 2), (2, 'a', 3), (2, 'b', 3), (3, 'a', 0), (3, 'b', 0)
+        ]);
+
+        assert_eq!(dfa.equivalence_classes().len(), dfa.minimal().states().len());
+        assert_eq!(dfa.equivalence_classes().len(), 2);
+    }
+
+    #[test]
+    fn current_survives_minimization() {
+        let mut dfa = redundant_ab_star();
+
+        dfa.insert_error_state();
+        dfa.walk(&'a').unwrap();
+        dfa.walk(&'b').unwrap();
+        dfa.walk(&'a').unwrap();
+        dfa.minimize_hopcroft();
+
+        assert!(dfa.states().contains_key(&dfa.current()));
+
+        dfa.walk(&'b').unwrap();
+
+        assert!(dfa.state_accept(dfa.current()));
+    }
+
+    #[test]
+    fn interning_round_trips_names() {
+        let mut dfa: Dfa<String> = Dfa::new();
+        let mut previous = 0;
+
+        for i in 0..30 {
+            let next = dfa.add_state(i == 29);
+
+            dfa.create_transition_between(&previous, &next, format!("symbol{}", i));
+            previous = next;
+        }
+
+        let (interned, table) = dfa.intern_symbols();
+
+        assert_eq!(table.len(), 30);
+        assert_eq!(table.name(table.id("symbol7").unwrap()), Some("symbol7"));
+        assert!(table.resolve(&interned) == dfa);
+        assert_eq!(interned.to_csv(), dfa.to_csv());
+        assert_eq!(interned.to_dot(), dfa.to_dot());
+
+        let input: Vec<Symbol> = (0..30)
+            .map(|i| interned.symbol(&format!("symbol{}", i)).unwrap().clone())
+            .collect();
+
+        assert_eq!(input[7].name(), "symbol7");
+        assert!(interned.accepts(&input));
+    }
+
+    #[test]
+    fn product_has_no_unreachable_states() {
+        let left = Dfa::from_transitions(0, &[1], &[(0, 'a', 1), (1, 'a', 0), (2, 'b', 0)]);
+        let right = ::regex::from_regex("a*b?").unwrap();
+
+        assert!(left.union(&right).get_unreachable_states().is_empty());
+        assert!(left.intersection(&right).get_unreachable_states().is_empty());
+        assert!(left.difference(&right).get_unreachable_states().is_empty());
+    }
+
+    #[test]
+    fn counts_of_multi_target() {
+        let dfa = multi_target();
+
+        assert_eq!(dfa.num_transitions(), 10);
+        assert_eq!(dfa.alphabet_sorted(), vec![&'a', &'b']);
+        assert_eq!(dfa.num_states(), dfa.states().len());
+    }
+
+    #[test]
+    fn removing_last_transition_by_symbol_prunes_alphabet() {
+        let mut dfa = Dfa::from_transitions(0, &[1], &[(0, 'a', 1), (0, 'b', 1)]);
+
+        dfa.remove_transition(0, &'b', 1);
+
+        assert!(!dfa.alphabet().contains(&'b'));
+        assert!(dfa.alphabet().contains(&'a'));
+    }
+
+    #[test]
+    fn canonical_form_of_empty_languages_are_equal() {
+        let empty = Dfa::<char>::new();
+        let dead = Dfa::from_transitions(7, &[], &[(7, 'a', 8), (8, 'b', 2)]);
+
+        assert!(empty.canonical_form() == dead.canonical_form());
+        assert!(empty.canonical_form() != ::regex::from_regex("a").unwrap().canonical_form());
+    }
+
+    #[test]
+    fn canonical_form_of_equivalent_automata_are_equal() {
+        let star = ::regex::from_regex("(ab)*").unwrap();
+        let unrolled = ::regex::from_regex("()|a(ba)*b").unwrap();
+        let renumbered = Dfa::from_transitions(4, &[4, 9], &[(4, 'a', 7), (7, 'b', 9), (9, 'a', 1), (1, 'b', 4)]);
+
+        assert!(star.canonical_form() == unrolled.canonical_form());
+        assert!(star.canonical_form() == renumbered.canonical_form());
+    }
+
+    #[test]
+    fn empty_languages_hash_equal() {
+        let empty = Dfa::<char>::new();
+        let dead = Dfa::from_transitions(5, &[], &[(5, 'a', 6)]);
+        let mut set = HashSet::new();
+
+        assert_eq!(hash_of(&empty), hash_of(&dead));
+
+        set.insert(empty);
+        assert!(set.contains(&Dfa::new()));
+    }
+
+    #[test]
+    fn equivalent_automata_hash_equal() {
+        let star = ::regex::from_regex("(ab)*").unwrap();
+        let renumbered = Dfa::from_transitions(4, &[4, 9], &[(4, 'a', 7), (7, 'b', 9), (9, 'a', 1), (1, 'b', 4)]);
+
+        assert_eq!(hash_of(&star), hash_of(&renumbered));
+        assert!(hash_of(&star) != hash_of(&::regex::from_regex("(ab)+").unwrap()));
+    }
+
+    #[test]
+    fn tokenize_fails_at_unexpected_char() {
+        let dfa = ::regex::from_regex("if| ").unwrap();
+
+        assert_eq!(dfa.tokenize("if @"), Err(LexError { offset: 3, line: 1, col: 4, unexpected: '@' }));
+    }
+
+    #[test]
+    fn tokenize_reader_gives_read_failures_apart() {
+        let dfa = ::regex::from_regex("if| ").unwrap();
+        let reader = io::BufReader::with_capacity(1, &b"if@ \xff"[..]);
+        let results: Vec<_> = dfa.tokenize_reader(reader).collect();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().as_ref().unwrap().lexeme, "if");
+        assert_eq!(results[1].as_ref().unwrap().as_ref().unwrap_err().unexpected, '@');
+        assert_eq!(results[2].as_ref().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn tokenize_gives_positions() {
+        let dfa = ::regex::from_regex("if|x+|\n| ").unwrap();
+        let tokens = dfa.tokenize("if x\nxx").unwrap();
+        let positions: Vec<(&str, usize, usize, usize)> = tokens
+            .iter()
+            .map(|t| (t.lexeme.as_str(), t.offset, t.line, t.col))
+            .collect();
+
+        assert_eq!(positions, vec![("if", 0, 1, 1), (" ", 2, 1, 3), ("x", 3, 1, 4), ("\n", 4, 1, 5), ("xx", 5, 2, 1)]);
+        assert!(tokens.iter().all(|t| dfa.state_accept(t.state)));
+    }
+
+    #[test]
+    fn hidden_trap_leaves_dashes() {
+        let mut dfa = Dfa::from_transitions(0, &[1], &[(0, 'a', 1), (1, 'b', 0)]);
+
+        dfa.insert_error_state();
+        dfa.hide_trap_in_output(true);
+
+        assert_eq!(dfa.to_csv(), "State,a,b\n-><0>,<1>,-\n*<1>,-,<0>\n");
+        assert!(dfa.is_complete());
+        assert!(!dfa.accepts_str("b"));
+    }
+
+    #[test]
+    fn longest_prefix() {
+        let dfa = ::regex::from_regex("ab").unwrap();
+
+        assert_eq!(dfa.longest_prefix_match(&['a', 'b', 'c']), Some(2));
+        assert_eq!(dfa.longest_prefix_match(&['a', 'c']), None);
+        assert_eq!(redundant_ab_star().longest_prefix_match(&['b']), Some(0));
+    }
+
+    #[test]
+    fn dot_styles_trap() {
+        let mut dfa = Dfa::from_transitions(0, &[1], &[(0, 'a', 1)]);
+        let trap = dfa.complete();
+
+        assert!(dfa.to_dot().contains(&format!("{} [shape=box, style=filled];", trap)));
+    }
+
+    #[test]
+    fn rename_middle_state() {
+        let mut dfa = Dfa::from_transitions(0, &[2], &[(0, 'a', 1), (1, 'b', 2), (1, 'a', 1)]);
+
+        dfa.set_state_label(1, "MIDDLE");
+        dfa.rename_state(1, 7).unwrap();
+
+        assert!(!dfa.states().contains_key(&1));
+        assert_eq!(dfa.iter_transitions().collect::<Vec<_>>(), vec![(0, &'a', 7), (7, &'a', 7), (7, &'b', 2)]);
+        assert_eq!(dfa.state_label(7), Some("MIDDLE"));
+        assert!(dfa.rename_state(7, 2).is_err());
+        assert!(dfa.accepts_str("aab"));
+    }
+
+    #[test]
+    fn cursor_feeds_one_symbol_at_a_time() {
+        let dfa = redundant_ab_star();
+        let mut cursor = dfa.cursor();
+
+        assert!(cursor.is_accepting());
+        assert!(cursor.feed(&'a'));
+        assert!(!cursor.is_accepting());
+        assert!(cursor.feed(&'b'));
+        assert!(cursor.is_accepting());
+        assert!(!cursor.feed(&'b'));
+        assert!(!cursor.is_accepting());
+        assert_eq!(cursor.state(), None);
+
+        cursor.reset();
+
+        assert_eq!(cursor.state(), Some(0));
     }
 }
@@ -0,0 +1,950 @@
+use dfa::{ Dfa, Transitable };
+use std::io::{ self, BufRead };
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{ self, Debug };
+
+/// Name of the initial nonterminal unless another is given
+pub const INITIAL_STATE_NAME: &str = "S";
+
+#[derive(PartialEq, Clone, Copy)]
+// enum Input: State Control for Token and Grammar recognizance
+// someword <- std token
+//
+// <S> ::= a<A> | b<B> | <>
+//  ^      ^       ^^^   ^^
+//  |      |       |||   ||
+//  |      |       |||   Epsilon
+//  |      |       Nonterminal Symbol (State)
+//  |      Terminal Symbol (Transition)
+//  State
+enum Input {
+    // Reading tokens as-is
+    // E.g.: if
+    // E.g.: else
+    Normal,
+    // Reading State definitions, like the left part of <S> ::= ...
+    StateDef,
+    // Reading the transitions, like the terminals of the right part of state definition
+    // E.g.: In `<S> ::= a<B> | b<E>`, the terminals are 'a' and 'b'
+    StateTransitions,
+    // Reading the transitions, like the nonterminals of the right part of state definition
+    // E.g.: In `<S> ::= e<C> | q<B> | <>`, the nonterminals are '<C>' '<B>' and '<>'.
+    // <> is aknowleged as Epsilon (Epsilon is a terminal symbol! But in this state it is aknowledged!)
+    // Nonterminal names may have many chars, e.g. `<IDENT>`, so they are collected until '>'
+    StateTransitionTarget,
+    // Reading a quoted terminal, a chain of symbols between two nonterminals
+    // E.g.: In `<S> ::= "if"<A>`, the terminal is "if"
+    QuotedTerminal,
+    // Reading a range of terminals, any of them goes to the same nonterminal
+    // E.g.: In `<S> ::= [a-z]<S>`, the terminals are 'a' to 'z'
+    TerminalRange,
+    // Reading the char after a '\', a terminal that would otherwise be part of the syntax
+    // E.g.: In `<S> ::= \|<A>`, the terminal is '|'
+    EscapedTerminal
+}
+
+#[derive(Debug)]
+// A terminal read in the right part of a state definition, waiting for its target
+enum Terminal {
+    // A single symbol or a quoted chain of them, like `a` or `"if"`
+    Word(Vec<char>),
+    // Any symbol of an inclusive range, like `[a-z]`
+    Range(char, char)
+}
+
+#[derive(Debug)]
+pub enum GrammarError {
+    Io(io::Error),
+    /// More than one terminal before a nonterminal, like `ab<A>`
+    NonRegular { line: usize, symbol: char },
+    /// A character out of place, like the `>` in `<A> ::= a>`
+    UnexpectedChar { line: usize, col: usize, found: char },
+    /// A `<` never closed in the line, like `<A ::= a<A>` or `<A> ::= a<A`
+    UnterminatedState { line: usize },
+    /// A `"` or `[` never closed in the line, like `<A> ::= "if<A>`
+    UnterminatedTerminal { line: usize },
+    /// A range not written as `[a-z]` or going backwards, like `[z-a]`
+    InvalidRange { line: usize, range: String },
+    /// A grammar with both right and left-linear productions, like `<S> ::= a<A>` and
+    /// `<A> ::= <A>b`
+    MixedLinearity { line: usize },
+    /// The same token written as-is in two lines
+    DuplicateToken { lexeme: String, first_line: usize, dup_line: usize }
+}
+
+impl fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GrammarError::Io(ref err) => write!(f, "could not read the grammar: {}", err),
+            GrammarError::NonRegular { line, symbol } => {
+                write!(f, "line {}: nonregular production, unexpected terminal '{}'", line, symbol)
+            },
+            GrammarError::UnexpectedChar { line, col, found } => {
+                write!(f, "line {}, column {}: unexpected '{}'", line, col, found)
+            },
+            GrammarError::UnterminatedState { line } => {
+                write!(f, "line {}: nonterminal is missing its closing '>'", line)
+            },
+            GrammarError::UnterminatedTerminal { line } => {
+                write!(f, "line {}: terminal is missing its closing quote or bracket", line)
+            },
+            GrammarError::InvalidRange { line, ref range } => {
+                write!(f, "line {}: invalid range '[{}]', expected something like '[a-z]'", line, range)
+            },
+            GrammarError::MixedLinearity { line } => {
+                write!(f, "line {}: left-linear production in a right-linear grammar or vice versa", line)
+            },
+            GrammarError::DuplicateToken { ref lexeme, first_line, dup_line } => {
+                write!(f, "line {}: token '{}' was already defined in line {}", dup_line, lexeme, first_line)
+            }
+        }
+    }
+}
+
+impl Error for GrammarError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            GrammarError::Io(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for GrammarError {
+    fn from(err: io::Error) -> Self {
+        GrammarError::Io(err)
+    }
+}
+
+/// Parse tokens and/or grammars into a new automaton, `<S>` being the initial nonterminal
+#[allow(dead_code)]
+pub fn parse_grammar<R: BufRead>(reader: R) -> Result<Dfa<char>, GrammarError> {
+    let mut dfa = Dfa::new();
+
+    parse_grammar_into(&mut dfa, reader, INITIAL_STATE_NAME)?;
+
+    Ok(dfa)
+}
+
+/// Parse tokens and/or grammars into a new automaton whose symbols are built by
+/// `symbol_parser`. Each terminal of the grammar is handed to it whole and is a single symbol,
+/// like `if` for `"if"<A>` or a token line, and each char of a range on its own. E.g. to build
+/// a `Dfa<u8>` or a `Dfa<String>` over already tokenized input
+#[allow(dead_code)]
+pub fn parse_grammar_with<T, F, R>(reader: R, symbol_parser: F) -> Result<Dfa<T>, GrammarError>
+    where T: Transitable + Debug, F: Fn(&str) -> T, R: BufRead
+{
+    let mut dfa = Dfa::new();
+
+    parse_grammar_into_with(&mut dfa, reader, symbol_parser, INITIAL_STATE_NAME)?;
+
+    Ok(dfa)
+}
+
+/// Parse tokens and/or grammars into an existing automaton, so many files can be merged into
+/// a single one. Nonterminals are not shared between calls. Grammars begin at the nonterminal
+/// named `start`, like `S` for `<S>`, any other name is an ordinary state
+pub fn parse_grammar_into<R: BufRead>(dfa: &mut Dfa<char>, reader: R, start: &str) -> Result<(), GrammarError> {
+    parse_into(dfa, reader, char_symbols, start)
+}
+
+/// Same as `parse_grammar_into`, with symbols built by `symbol_parser` as in
+/// `parse_grammar_with`
+pub fn parse_grammar_into_with<T, F, R>(dfa: &mut Dfa<T>, reader: R, symbol_parser: F, start: &str) -> Result<(), GrammarError>
+    where T: Transitable + Debug, F: Fn(&str) -> T, R: BufRead
+{
+    parse_into(dfa, reader, |lexeme: &str| vec![symbol_parser(lexeme)], start)
+}
+
+/// Symbols of a `Dfa<char>`, every char of a terminal is one
+fn char_symbols(lexeme: &str) -> Vec<char> {
+    lexeme.chars().collect()
+}
+
+/// Index of the nonterminal `name`. If state doesn't exists yet, we need to map it and hope that
+/// it will be defined in the future :P
+fn state_of<T: Transitable>(dfa: &mut Dfa<T>, grammar_mapper: &mut HashMap<String, usize>, name: &str, start: &str, form: Linearity) -> usize {
+    // Left-linear grammars end at `<S>`, so it is not the initial state
+    if name == start && form == Linearity::Right {
+        return *dfa.initial();
+    }
+
+    if let Some(index) = grammar_mapper.get(name) {
+        return *index;
+    }
+
+    let state = dfa.add_state(false);
+    debug!("Indexing {} to {}", name, state);
+    grammar_mapper.insert(name.to_string(), state);
+
+    state
+}
+
+/// Create the transitions spelling the symbols of `word` from the current state to `target`,
+/// with a new state between each pair of them. The current state is kept
+fn spell<T, F>(dfa: &mut Dfa<T>, symbols: &F, word: &[char], target: usize)
+    where T: Transitable + Debug, F: Fn(&str) -> Vec<T>
+{
+    let origin = dfa.current();
+    let mut spelled = symbols(&word.iter().collect::<String>());
+    let last = spelled.pop().expect("Terminals are never empty");
+
+    for symbol in spelled {
+        let state = dfa.add_state(false);
+        dfa.create_transition_and_walk(symbol, state);
+    }
+
+    dfa.create_transition(last, target);
+    dfa.set_current(origin).expect("This should not happen!");
+}
+
+/// Create the transitions of `terminal` from the current state to `target`
+fn emit<T, F>(dfa: &mut Dfa<T>, symbols: &F, terminal: &Terminal, target: usize)
+    where T: Transitable + Debug, F: Fn(&str) -> Vec<T>
+{
+    match *terminal {
+        Terminal::Word(ref word) => spell(dfa, symbols, word, target),
+        Terminal::Range(start, end) => {
+            for c in start..=end {
+                spell(dfa, symbols, &[c], target);
+            }
+        }
+    }
+}
+
+/// Parse the inside of `[a-z]`, the range must not go backwards
+fn parse_range(range: &str, line: usize) -> Result<Terminal, GrammarError> {
+    let chars: Vec<char> = range.chars().collect();
+
+    match chars.as_slice() {
+        &[start, '-', end] if start <= end => Ok(Terminal::Range(start, end)),
+        _ => Err(GrammarError::InvalidRange { line, range: range.to_string() })
+    }
+}
+
+/// Terminal written as `\c`, e.g. `\n` is a newline and `\|` the `|` terminal
+fn unescape(c: char) -> Option<char> {
+    match c {
+        's' | ' ' => Some(' '),
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        '|' | '<' | '>' | '\\' | '"' | '[' | '#' => Some(c),
+        _ => None
+    }
+}
+
+/// Part of `line` before a `#` comment, a `#` between quotes or brackets is a terminal, as
+/// well as an escaped `\#`
+fn strip_comment(line: &str) -> &str {
+    let mut closing: Option<char> = None;
+    let mut chars = line.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match (closing, c) {
+            (None, '\\') => { chars.next(); },
+            (None, '#') => return &line[..i],
+            (None, '"') => closing = Some('"'),
+            (None, '[') => closing = Some(']'),
+            (Some(end), c) if c == end => closing = None,
+            _ => ()
+        }
+    }
+
+    line
+}
+
+#[derive(PartialEq, Clone, Copy)]
+// A piece of an alternative, enough to tell its form without building anything
+enum Part {
+    // A single, quoted or range terminal, by its first char
+    Terminal(char),
+    Nonterminal
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+// Form of a grammar, given by the first of its productions having both a terminal and a
+// nonterminal
+enum Linearity {
+    // `<A> ::= a<B>`, read from the initial state on
+    Right,
+    // `<A> ::= <B>a`, read backwards from `<S>`, which is where the word ends
+    Left
+}
+
+/// Name and alternatives of the state definition in `line`, `None` if it is not one
+fn production(line: &str) -> Option<(&str, Vec<Vec<Part>>)> {
+    let line = strip_comment(line).trim();
+
+    if !line.starts_with('<') {
+        return None;
+    }
+
+    let split = line.find("::=")?;
+    let name = line[..split].trim().trim_start_matches('<').trim_end_matches('>').trim();
+    let mut chars = line[split + 3..].chars();
+    let mut alternatives = vec![Vec::new()];
+
+    while let Some(c) = chars.next() {
+        let part = match c {
+            ' ' => continue,
+            '|' => {
+                alternatives.push(Vec::new());
+                continue;
+            },
+            '<' => {
+                // `<>` is the empty word, not a nonterminal
+                if chars.by_ref().take_while(|&c| c != '>').count() == 0 {
+                    continue;
+                }
+
+                Part::Nonterminal
+            },
+            '\\' => Part::Terminal(chars.next().and_then(unescape).unwrap_or(c)),
+            '"' | '[' => {
+                let closing = if c == '"' { '"' } else { ']' };
+                let inside: Vec<char> = chars.by_ref().take_while(|&c| c != closing).collect();
+
+                Part::Terminal(inside.first().cloned().unwrap_or(c))
+            },
+            c => Part::Terminal(c)
+        };
+
+        alternatives.last_mut().expect("Never empty").push(part);
+    }
+
+    Some((name, alternatives))
+}
+
+/// Form of the alternative `parts`, if it has a terminal and a nonterminal
+fn linearity(parts: &[Part]) -> Option<Linearity> {
+    match parts {
+        [Part::Terminal(_), Part::Nonterminal, ..] => Some(Linearity::Right),
+        [Part::Nonterminal, Part::Terminal(_), ..] => Some(Linearity::Left),
+        _ => None
+    }
+}
+
+/// Form of the grammar each of `lines` belongs to, grammars begin at each definition of `start`.
+/// Grammars without a production telling their form are right-linear
+fn linearity_of_lines(lines: &[String], start: &str) -> Result<Vec<Linearity>, GrammarError> {
+    let mut forms = Vec::with_capacity(lines.len());
+    let mut form = None;
+
+    for (n, line) in lines.iter().enumerate() {
+        let (name, alternatives) = match production(line) {
+            Some(p) => p,
+            None => continue
+        };
+
+        if name == start {
+            forms.resize(n, form.unwrap_or(Linearity::Right));
+            form = None;
+        }
+
+        for found in alternatives.iter().filter_map(|parts| linearity(parts)) {
+            match form {
+                Some(f) if f != found => return Err(GrammarError::MixedLinearity { line: n + 1 }),
+                _ => form = Some(found)
+            }
+        }
+    }
+
+    forms.resize(lines.len(), form.unwrap_or(Linearity::Right));
+
+    Ok(forms)
+}
+
+/// Create the transitions of a left-linear alternative of the current state: `<B>a` from the
+/// state of `<B>`, or `a` from the initial state when there is no `source`. With no terminal
+/// it is an epsilon transition, the same way `<B>` or `<>`
+fn emit_left<T, F>(dfa: &mut Dfa<T>, symbols: &F, source: Option<usize>, terminal: Option<Terminal>)
+    where T: Transitable + Debug, F: Fn(&str) -> Vec<T>
+{
+    let target = dfa.current();
+    let origin = source.unwrap_or(*dfa.initial());
+
+    match terminal {
+        Some(t) => {
+            dfa.set_current(origin).expect("This should not happen!");
+            emit(dfa, symbols, &t, target);
+            dfa.set_current(target).expect("This should not happen!");
+        },
+        None => dfa.create_epsilon_transition_between(&origin, &target)
+    }
+}
+
+/// The parser behind `parse_grammar_into` and `parse_grammar_into_with`, `symbols` gives the
+/// symbols spelling each terminal
+fn parse_into<T, F, R>(dfa: &mut Dfa<T>, reader: R, symbols: F, start: &str) -> Result<(), GrammarError>
+    where T: Transitable + Debug, F: Fn(&str) -> Vec<T>, R: BufRead
+{
+    let mut reading = Input::Normal;
+    let mut temp_transition: Option<Terminal> = None;
+    let mut grammar_mapper: HashMap<String, usize> = HashMap::new();
+    // Name of the nonterminal being read, between '<' and '>'
+    let mut nonterminal = String::new();
+    // Inside of a range being read, between '[' and ']'
+    let mut range = String::new();
+    // Name of the nonterminal whose transitions are being read, labels its accepting states
+    let mut defining = String::new();
+    // Token being read as-is, labels its accepting state
+    let mut token = String::new();
+    // Line where each token was read, to catch it being written again
+    let mut token_lines: HashMap<String, usize> = HashMap::new();
+    // Nonterminal read before the terminal in left-linear grammars, like `<B>` in `<B>a`
+    let mut source: Option<usize> = None;
+
+    // A previous grammar may have left the DFA anywhere, tokens start from the initial state
+    dfa.rewind();
+
+    // The form of a grammar may only be known after some of its lines, so it is read first
+    let lines = reader.lines().collect::<Result<Vec<String>, io::Error>>()?;
+    let forms = linearity_of_lines(&lines, start)?;
+
+    for (n, full_line) in lines.iter().enumerate() {
+        let line_number = n + 1;
+        let form = forms[n];
+        let uncommented = strip_comment(full_line);
+        // A `\r` left by CRLF endings or trailing spaces must not end up in a token
+        let mut line = uncommented.trim_end();
+
+        // Unless it is an escaped space, `\ `
+        if line.ends_with('\\') && line.len() < uncommented.len() {
+            let space = uncommented[line.len()..].chars().next().map_or(0, char::len_utf8);
+
+            line = &uncommented[..line.len() + space];
+        }
+
+        debug!("Line: `{}`", line);
+
+        // A line with only a comment is not an empty token
+        if uncommented.len() < full_line.len() && line.trim().is_empty() {
+            continue;
+        }
+
+        for (col, c) in line.chars().enumerate().map(|(i, c)| (i + 1, c)) {
+            match reading {
+                Input::Normal if c != ' ' => {
+                    if c == '<' {
+                        // Not a token after all
+                        token.clear();
+                        reading = Input::StateDef;
+                    } else {
+                        token.push(c);
+                    }
+                },
+                Input::StateDef if c != ' ' => {
+                    match c {
+                        '<' => continue,
+                        '>' if nonterminal.is_empty() => {
+                            return Err(GrammarError::UnexpectedChar { line: line_number, col, found: c });
+                        },
+                        '>' => {
+                            // Add to mapper which index solves to current State, e.g. <A> maps to
+                            // index 3, <E> to index 8...
+                            let index = state_of(dfa, &mut grammar_mapper, &nonterminal, start, form);
+
+                            // If current state is the initial, rewind to it, else go to new state
+                            if index == *dfa.initial() { dfa.rewind(); }
+                            else { dfa.set_current(index).expect("This should not happen!"); }
+
+                            // Words of left-linear grammars are accepted once they reach `<S>`
+                            if nonterminal == start && form == Linearity::Left {
+                                dfa.set_current_state_accept(true);
+                                dfa.set_state_label(index, &nonterminal);
+                            }
+
+                            defining = nonterminal.clone();
+                            nonterminal.clear();
+                            reading = Input::StateTransitions;
+                        },
+                        _ => nonterminal.push(c)
+                    }
+                },
+                Input::StateTransitions => {
+                    match c {
+                        '<'       => reading = Input::StateTransitionTarget,
+                        // Epsilon Transitions, `b` in <A> ::= a<A> | b | c<C> or in
+                        // <B> ::= a<B> | b
+                        '|' | ' ' if form == Linearity::Left && (source.is_some() || temp_transition.is_some()) => {
+                            emit_left(dfa, &symbols, source.take(), temp_transition.take());
+                        },
+                        '|' | ' ' => {
+                            if let Some(t) = temp_transition.take() {
+                                let empty_state = dfa.add_state(true);
+                                warn!("Creating new empty-state to {:?}: {}", t, empty_state);
+                                dfa.set_state_label(empty_state, &defining);
+                                emit(dfa, &symbols, &t, empty_state);
+                            }
+                        },
+                        ':' | '=' => continue,
+                        '"' if temp_transition.is_none() => {
+                            temp_transition = Some(Terminal::Word(Vec::new()));
+                            reading = Input::QuotedTerminal;
+                        },
+                        '[' if temp_transition.is_none() => {
+                            range.clear();
+                            reading = Input::TerminalRange;
+                        },
+                        '\\' => reading = Input::EscapedTerminal,
+                        '>' => {
+                            return Err(GrammarError::UnexpectedChar { line: line_number, col, found: c });
+                        },
+                        ch if ch != ' ' => {
+                            if temp_transition.is_none() {
+                                temp_transition = Some(Terminal::Word(vec![ch]));
+                            } else {
+                                // If there is two transitions, the grammar is not regular
+                                return Err(GrammarError::NonRegular { line: line_number, symbol: ch });
+                            }
+                        },
+                        _ => ()
+                    }
+                },
+                Input::StateTransitionTarget if c != ' ' => {
+                    match c {
+                        // Nested `<`, like `a<<A>`
+                        '<' => {
+                            return Err(GrammarError::UnexpectedChar { line: line_number, col, found: c });
+                        },
+                        // Epsilon (aka <>), which in left-linear grammars comes from the initial state
+                        '>' if nonterminal.is_empty() && form == Linearity::Left => {
+                            if temp_transition.is_none() {
+                                emit_left(dfa, &symbols, None, None);
+                            }
+
+                            reading = Input::StateTransitions;
+                        },
+                        '>' if nonterminal.is_empty() => {
+                            if temp_transition.is_none() {
+                                let current = dfa.current();
+
+                                dfa.set_current_state_accept(true);
+                                dfa.set_state_label(current, &defining);
+                            }
+
+                            reading = Input::StateTransitions;
+                        },
+                        // Only known to be `<B>a` or `<B>` at the end of the alternative
+                        '>' if form == Linearity::Left => {
+                            if source.is_some() {
+                                return Err(GrammarError::NonRegular { line: line_number, symbol: '<' });
+                            }
+
+                            source = Some(state_of(dfa, &mut grammar_mapper, &nonterminal, start, form));
+                            nonterminal.clear();
+                            reading = Input::StateTransitions;
+                        },
+                        '>' => {
+                            let target = state_of(dfa, &mut grammar_mapper, &nonterminal, start, form);
+
+                            if let Some(t) = temp_transition.take() {
+                                emit(dfa, &symbols, &t, target)
+                            } else {
+                                debug!("Epsilon-transition to <{}>", nonterminal);
+                                dfa.create_epsilon_transition(target);
+                            }
+
+                            nonterminal.clear();
+                            reading = Input::StateTransitions;
+                        },
+                        _ => nonterminal.push(c)
+                    }
+                },
+                Input::QuotedTerminal => {
+                    if let Some(Terminal::Word(ref mut word)) = temp_transition {
+                        match c {
+                            '"' if word.is_empty() => {
+                                return Err(GrammarError::UnexpectedChar { line: line_number, col, found: c });
+                            },
+                            '"' => reading = Input::StateTransitions,
+                            _   => word.push(c)
+                        }
+                    }
+                },
+                Input::TerminalRange => {
+                    if c == ']' {
+                        temp_transition = Some(parse_range(&range, line_number)?);
+                        reading = Input::StateTransitions;
+                    } else {
+                        range.push(c);
+                    }
+                },
+                Input::EscapedTerminal => {
+                    let ch = unescape(c).ok_or(GrammarError::UnexpectedChar { line: line_number, col, found: c })?;
+
+                    if temp_transition.is_none() {
+                        temp_transition = Some(Terminal::Word(vec![ch]));
+                    } else {
+                        return Err(GrammarError::NonRegular { line: line_number, symbol: ch });
+                    }
+
+                    reading = Input::StateTransitions;
+                },
+                _ => ()
+            }
+        }
+
+        // A state definition or target left open: `<A ::= ...` or `... | a<A`
+        match reading {
+            Input::StateDef if !line.trim().is_empty() => {
+                return Err(GrammarError::UnterminatedState { line: line_number });
+            },
+            Input::StateTransitionTarget => {
+                return Err(GrammarError::UnterminatedState { line: line_number });
+            },
+            Input::QuotedTerminal | Input::TerminalRange | Input::EscapedTerminal => {
+                return Err(GrammarError::UnterminatedTerminal { line: line_number });
+            },
+            _ => ()
+        }
+
+        // Line ends like: <A> ::= a<A> | b<B> | c
+        // and so 'c' is not parsed
+        if form == Linearity::Left {
+            if source.is_some() || temp_transition.is_some() {
+                emit_left(dfa, &symbols, source.take(), temp_transition.take());
+            }
+        } else if let Some(t) = temp_transition.take() {
+            let empty_state = dfa.add_state(true);
+            warn!("Creating new empty-state to {:?}: {}", t, empty_state);
+            dfa.set_state_label(empty_state, &defining);
+            emit(dfa, &symbols, &t, empty_state);
+        }
+
+        if reading == Input::Normal {
+            // The token is spelled once whole, so it is handed to `symbols` as a single lexeme
+            if !token.is_empty() {
+                for symbol in symbols(&token) {
+                    let state_index = dfa.add_state(false);
+                    dfa.create_transition_and_walk(symbol, state_index);
+                }
+            }
+
+            // We had finished the current line, so the last state accept the current token
+            dfa.set_current_state_accept(true);
+
+            if !token.is_empty() {
+                if let Some(&first_line) = token_lines.get(&token) {
+                    return Err(GrammarError::DuplicateToken { lexeme: token, first_line, dup_line: line_number });
+                }
+
+                let current = dfa.current();
+
+                dfa.set_state_label(current, &token);
+                token_lines.insert(token.clone(), line_number);
+                token.clear();
+            }
+
+            dfa.rewind();
+        } else {
+            // Finished reading a line of grammar, must reset the state to keep reading
+            reading = Input::StateDef;
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that every production of `spec` is linear, that is `a<A>` in right-linear grammars
+/// or `<A>a` in left-linear ones, besides `a`, `<A>` and `<>`, where `a` is a single, quoted or
+/// range terminal. Unlike the parser, which stops at the first problem, every violation is
+/// collected, like two terminals in `ab<A>`, a terminal after `<A>b` or two nonterminals in
+/// `<A><B>`, and grammars mixing both forms. Each grammar begins at the `start` nonterminal,
+/// so grammars of different forms may follow each other. Lines of tokens are not checked
+#[allow(dead_code)]
+pub fn validate_regular(spec: &str, start: &str) -> Result<(), Vec<GrammarError>> {
+    let mut errors = Vec::new();
+    let mut form = None;
+
+    for (n, line) in spec.lines().enumerate() {
+        let line_number = n + 1;
+        let (name, alternatives) = match production(line) {
+            Some(p) => p,
+            None => continue
+        };
+
+        if name == start {
+            form = None;
+        }
+
+        for parts in alternatives {
+            let (mut terminals, mut nonterminals) = (0, 0);
+
+            for part in &parts {
+                let symbol = match *part {
+                    Part::Terminal(c) if terminals > 0 => c,
+                    Part::Nonterminal if nonterminals > 0 => '<',
+                    Part::Terminal(_) => { terminals += 1; continue; },
+                    Part::Nonterminal => { nonterminals += 1; continue; }
+                };
+
+                errors.push(GrammarError::NonRegular { line: line_number, symbol });
+            }
+
+            match (form, linearity(&parts)) {
+                (Some(a), Some(b)) if a != b => errors.push(GrammarError::MixedLinearity { line: line_number }),
+                (None, found) => form = found,
+                _ => ()
+            }
+        }
+    }
+
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines `validate_regular` reports as mixing linearities
+    fn mixed_lines(spec: &str, start: &str) -> Vec<usize> {
+        validate_regular(spec, start)
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|e| match e {
+                GrammarError::MixedLinearity { line } => Some(line),
+                _ => None
+            })
+            .collect()
+    }
+
+    /// The error parsing `spec` fails with
+    fn error_of(spec: &str) -> GrammarError {
+        match parse_grammar(spec.as_bytes()) {
+            Err(err) => err,
+            Ok(_) => panic!("{:?} parsed", spec)
+        }
+    }
+
+    /// A reader whose every read fails
+    struct Failing;
+
+    impl io::Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("unplugged"))
+        }
+    }
+
+    #[test]
+    fn epsilon_rule_takes_the_target_language() {
+        // `<A> ::= <B>` makes `A` accept like `B` and follow its `b` loop
+        let mut dfa = parse_grammar(include_str!("../tests/epsilon.in").as_bytes()).unwrap();
+
+        dfa.determinize();
+
+        for word in &["a", "ab", "acc", "accbb"] {
+            assert!(dfa.accepts_str(word), "{}", word);
+        }
+
+        for word in &["", "b", "abc", "aa"] {
+            assert!(!dfa.accepts_str(word), "{}", word);
+        }
+    }
+
+    #[test]
+    fn malformed_line_gives_its_number() {
+        match error_of("<S> ::= a<A>\n<A> ::= a>\n") {
+            GrammarError::UnexpectedChar { line, col, found } => assert_eq!((line, col, found), (2, 10, '>')),
+            other => panic!("unexpected {:?}", other)
+        }
+    }
+
+    #[test]
+    fn every_error_variant() {
+        assert!(matches!(parse_grammar(io::BufReader::new(Failing)), Err(GrammarError::Io(_))));
+
+        match error_of("<S> ::= ab<A>\n") {
+            GrammarError::NonRegular { line, symbol } => assert_eq!((line, symbol), (1, 'b')),
+            other => panic!("unexpected {:?}", other)
+        }
+
+        match error_of("<S> ::= a\n<A> ::= a<A\n") {
+            GrammarError::UnterminatedState { line } => assert_eq!(line, 2),
+            other => panic!("unexpected {:?}", other)
+        }
+
+        match error_of("<S> ::= \"if<A>\n") {
+            GrammarError::UnterminatedTerminal { line } => assert_eq!(line, 1),
+            other => panic!("unexpected {:?}", other)
+        }
+
+        match error_of("<S> ::= [z-a]<S>\n") {
+            GrammarError::InvalidRange { line, range } => assert_eq!((line, range.as_str()), (1, "z-a")),
+            other => panic!("unexpected {:?}", other)
+        }
+
+        match error_of("<S> ::= a<A>\n<A> ::= <A>b\n") {
+            GrammarError::MixedLinearity { line } => assert_eq!(line, 2),
+            other => panic!("unexpected {:?}", other)
+        }
+    }
+
+    #[test]
+    fn multi_char_nonterminals() {
+        let mut dfa = Dfa::new();
+
+        parse_grammar_into(&mut dfa, "<START> ::= a<ACCEPT>\n<ACCEPT> ::= <>\n".as_bytes(), "START").unwrap();
+
+        let accept = dfa.transition(*dfa.initial(), &'a').unwrap();
+
+        assert!(accept != *dfa.initial());
+        assert_eq!(dfa.state_label(accept), Some("ACCEPT"));
+        assert!(dfa.accepts_str("a"));
+    }
+
+    #[test]
+    fn quoted_terminal_spells_its_chars() {
+        let dfa = parse_grammar("<S> ::= \"else\"\n".as_bytes()).unwrap();
+
+        assert_eq!(dfa.states().len(), 5);
+        assert!(dfa.accepts_str("else"));
+        assert!(!dfa.accepts_str("els"));
+    }
+
+    #[test]
+    fn range_gives_a_transition_per_char() {
+        let dfa = parse_grammar(include_str!("../tests/range.in").as_bytes()).unwrap();
+        let initial = *dfa.initial();
+
+        // `[a-c]<S>` loops back once per char, `[0-1]` leaves once per char
+        assert_eq!(dfa.transitions_from(initial).filter(|t| t.1 == initial).count(), 3);
+        assert_eq!(dfa.num_transitions(), 5);
+        assert!(dfa.accepts_str("cab1"));
+        assert!(!dfa.accepts_str("cab"));
+        assert!(!dfa.accepts_str("d0"));
+    }
+
+    #[test]
+    fn byte_grammar() {
+        let dfa = parse_grammar_with("<S> ::= a<S> | b\n".as_bytes(), |s| s.as_bytes()[0]).unwrap();
+
+        assert!(dfa.accepts(b"aab"));
+        assert!(!dfa.accepts(b"aa"));
+    }
+
+    #[test]
+    fn string_symbols_are_whole_terminals() {
+        let grammar = "while\n<S> ::= \"if\"<A> | \"else\"\n<A> ::= \"then\" | [x-y]\n";
+        let dfa = parse_grammar_with(grammar.as_bytes(), |s| s.to_string()).unwrap();
+        let words = |ws: &[&str]| -> Vec<String> { ws.iter().map(|w| w.to_string()).collect() };
+
+        assert_eq!(dfa.alphabet(), &words(&["else", "if", "then", "while", "x", "y"]).into_iter().collect());
+        assert!(dfa.accepts(&words(&["if", "then"])));
+        assert!(dfa.accepts(&words(&["if", "y"])));
+        assert!(dfa.accepts(&words(&["while"])));
+        assert!(!dfa.accepts(&words(&["i", "f", "then"])));
+    }
+
+    #[test]
+    fn char_symbols_spell_quoted_terminals() {
+        let dfa = parse_grammar("<S> ::= \"if\"<A>\n<A> ::= x\n".as_bytes()).unwrap();
+
+        assert!(dfa.accepts_str("ifx"));
+        assert_eq!(dfa.alphabet().len(), 3);
+    }
+
+    #[test]
+    fn comments_are_ignored() {
+        let mut dfa = parse_grammar(include_str!("../tests/comments.in").as_bytes()).unwrap();
+
+        dfa.determinize();
+
+        assert!(dfa.accepts_str("se"));
+        assert!(dfa.accepts_str("senao"));
+        assert!(dfa.accepts_str("#abc"));
+        assert!(!dfa.accepts_str("if"));
+        assert!(!dfa.accepts_str("Keywords"));
+    }
+
+    #[test]
+    fn crlf_gives_the_same_dfa() {
+        let lf = "se\n<S> ::= a<A> | b  \n<A> ::= a<A> | <>\n";
+
+        assert!(parse_grammar(lf.replace('\n', "\r\n").as_bytes()).unwrap() == parse_grammar(lf.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn validate_regular_reports_every_violation() {
+        for spec in &["<S> ::= ab<A>\n", "<S> ::= a<A>b\n", "<S> ::= a<A>\n<A> ::= <A>b\n", "<S> ::= <A><B>\n"] {
+            assert!(validate_regular(spec, INITIAL_STATE_NAME).is_err(), "{}", spec);
+        }
+
+        let errors = validate_regular("<S> ::= ab<A>\n<A> ::= cd<S>\n", INITIAL_STATE_NAME).unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert!(validate_regular(include_str!("../tests/multi-grammar.in"), INITIAL_STATE_NAME).is_ok());
+    }
+
+    #[test]
+    fn left_linear_matches_right_linear() {
+        let mut left = parse_grammar("<S> ::= <S>a | <>\n".as_bytes()).unwrap();
+        let mut right = parse_grammar("<S> ::= a<S> | <>\n".as_bytes()).unwrap();
+
+        left.determinize();
+        right.determinize();
+
+        assert!(left.equivalent(&right));
+        assert!(left.accepts_str("aaa"));
+
+        // `a+b+` written both ways
+        let mut left = parse_grammar(include_str!("../tests/left-linear.in").as_bytes()).unwrap();
+        let mut right = parse_grammar("<S> ::= a<A>\n<A> ::= a<A> | b<B>\n<B> ::= b<B> | <>\n".as_bytes()).unwrap();
+
+        left.determinize();
+        right.determinize();
+
+        assert!(left.equivalent(&right));
+        assert!(left.accepts_str("aabbb"));
+        assert!(!left.accepts_str("ba"));
+    }
+
+    #[test]
+    fn configurable_start() {
+        let mut dfa = Dfa::new();
+
+        parse_grammar_into(&mut dfa, "<START> ::= a<S>\n<S> ::= b\n".as_bytes(), "START").unwrap();
+        dfa.determinize();
+
+        assert!(dfa.accepts_str("ab"));
+        assert!(!dfa.accepts_str("b"));
+    }
+
+    #[test]
+    fn validate_regular_splits_grammars_at_start() {
+        let spec = "<START> ::= a<A>\n<A> ::= b\n<START> ::= <B>c\n<B> ::= d\n";
+
+        assert!(validate_regular(spec, "START").is_ok());
+        assert_eq!(mixed_lines(spec, INITIAL_STATE_NAME), vec![3]);
+    }
+
+    #[test]
+    fn validate_regular_does_not_reset_at_s_with_other_start() {
+        let spec = "<START> ::= a<S>\n<S> ::= <B>c\n";
+
+        assert_eq!(mixed_lines(spec, "START"), vec![2]);
+        assert!(validate_regular(spec, INITIAL_STATE_NAME).is_ok());
+    }
+
+    #[test]
+    fn escaped_pipe_is_a_terminal() {
+        let mut dfa = parse_grammar("<S> ::= \\|<A>\n<A> ::= <>\n".as_bytes()).unwrap();
+
+        dfa.determinize();
+
+        assert!(dfa.accepts_str("|"));
+        assert_eq!(dfa.words_up_to(3), vec![vec!['|']]);
+    }
+
+    #[test]
+    fn duplicate_token() {
+        match error_of("se\nsenao\nse\n") {
+            GrammarError::DuplicateToken { lexeme, first_line, dup_line } => {
+                assert_eq!((lexeme.as_str(), first_line, dup_line), ("se", 1, 3))
+            },
+            other => panic!("unexpected {:?}", other)
+        }
+    }
+}
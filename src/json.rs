@@ -0,0 +1,237 @@
+// Just enough JSON to read and write the DFA interchange format, see `Dfa::to_json`
+use std::error::Error;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+#[derive(Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// Only integers are supported, they are all a DFA needs
+    Number(i64),
+    String(String),
+    Array(Vec<Value>),
+    /// Keys in the order they were read
+    Object(Vec<(String, Value)>)
+}
+
+impl Value {
+    /// Value of `key` if this is an object having it
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Object(ref entries) => entries.iter().find(|e| e.0 == key).map(|e| &e.1),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum JsonError {
+    /// Malformed JSON, at the given byte offset
+    Syntax { offset: usize },
+    /// Well-formed JSON that is not what was expected
+    Schema(String)
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JsonError::Syntax { offset } => write!(f, "invalid JSON at byte {}", offset),
+            JsonError::Schema(ref msg) => write!(f, "unexpected JSON: {}", msg)
+        }
+    }
+}
+
+impl Error for JsonError {}
+
+/// Quote and escape `s` as a JSON string
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"'  => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted += format!("\\u{:04x}", c as u32).as_str(),
+            c => quoted.push(c)
+        }
+    }
+
+    quoted.push('"');
+
+    quoted
+}
+
+/// Parse a whole JSON document
+pub fn parse(s: &str) -> Result<Value, JsonError> {
+    let mut parser = Parser { chars: s.char_indices().peekable(), len: s.len() };
+    let value = parser.value()?;
+
+    parser.skip_whitespace();
+
+    match parser.chars.next() {
+        None => Ok(value),
+        Some((offset, _)) => Err(JsonError::Syntax { offset })
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+    len: usize
+}
+
+impl<'a> Parser<'a> {
+    fn offset(&mut self) -> usize {
+        let len = self.len;
+
+        self.chars.peek().map_or(len, |c| c.0)
+    }
+
+    fn error<V>(&mut self) -> Result<V, JsonError> {
+        Err(JsonError::Syntax { offset: self.offset() })
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !c.is_whitespace() { break; }
+            self.chars.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        match self.chars.peek() {
+            Some(&(_, c)) if c == expected => {
+                self.chars.next();
+                Ok(())
+            },
+            _ => self.error()
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, JsonError> {
+        for c in word.chars() {
+            self.expect(c)?;
+        }
+
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Value, JsonError> {
+        self.skip_whitespace();
+
+        match self.chars.peek().map(|c| c.1) {
+            Some('n') => self.keyword("null", Value::Null),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('"') => self.string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            _ => self.error()
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.offset();
+        let mut digits = String::new();
+
+        while let Some(&(_, c)) = self.chars.peek() {
+            if c != '-' && !c.is_ascii_digit() { break; }
+
+            digits.push(c);
+            self.chars.next();
+        }
+
+        digits.parse().map(Value::Number).map_err(|_| JsonError::Syntax { offset: start })
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        let mut s = String::new();
+
+        self.expect('"')?;
+
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(s),
+                Some((offset, '\\')) => {
+                    let escaped = match self.chars.next() {
+                        Some((_, '"'))  => '"',
+                        Some((_, '\\')) => '\\',
+                        Some((_, '/'))  => '/',
+                        Some((_, 'n'))  => '\n',
+                        Some((_, 'r'))  => '\r',
+                        Some((_, 't'))  => '\t',
+                        Some((_, 'b'))  => '\u{8}',
+                        Some((_, 'f'))  => '\u{c}',
+                        Some((_, 'u'))  => {
+                            let hex: String = (0..4).filter_map(|_| self.chars.next()).map(|c| c.1).collect();
+
+                            u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(::std::char::from_u32)
+                                .ok_or(JsonError::Syntax { offset })?
+                        },
+                        _ => return Err(JsonError::Syntax { offset })
+                    };
+
+                    s.push(escaped);
+                },
+                Some((_, c)) => s.push(c),
+                None => return self.error()
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, JsonError> {
+        let mut values = Vec::new();
+
+        self.expect('[')?;
+        self.skip_whitespace();
+
+        if self.expect(']').is_ok() {
+            return Ok(Value::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+
+            if self.expect(',').is_err() {
+                self.expect(']')?;
+
+                return Ok(Value::Array(values));
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, JsonError> {
+        let mut entries = Vec::new();
+
+        self.expect('{')?;
+        self.skip_whitespace();
+
+        if self.expect('}').is_ok() {
+            return Ok(Value::Object(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+
+            self.skip_whitespace();
+            self.expect(':')?;
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+
+            if self.expect(',').is_err() {
+                self.expect('}')?;
+
+                return Ok(Value::Object(entries));
+            }
+        }
+    }
+}
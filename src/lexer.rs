@@ -0,0 +1,190 @@
+use dfa::Dfa;
+use std::collections::{ HashSet, VecDeque };
+use std::error::Error;
+use std::fmt;
+use std::io::{ self, BufRead };
+use std::str;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token {
+    pub lexeme: String,
+    /// Byte offset of the lexeme in the input
+    pub offset: usize,
+    /// Line of the first char, from 1
+    pub line: usize,
+    /// Column of the first char in its line, from 1 and counting chars, not bytes
+    pub col: usize,
+    /// Accepting state the lexeme ended in, see `Dfa::state_label` for its name
+    pub state: usize
+}
+
+/// No token starts with the `unexpected` char, where the longest match broke
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexError {
+    /// Byte offset of the char in the input
+    pub offset: usize,
+    /// Line of the char, from 1
+    pub line: usize,
+    /// Column of the char in its line, from 1 and counting chars, not bytes
+    pub col: usize,
+    pub unexpected: char
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: unexpected '{}'", self.line, self.col, self.unexpected)
+    }
+}
+
+impl Error for LexError {}
+
+// Longest-match lexer over a reader, keeps only the chars not yet turned into tokens
+struct Tokens<'a, R> {
+    dfa: &'a Dfa<char>,
+    // States no token can end from, like the trap of a complete DFA, where a match gives up
+    dead: HashSet<usize>,
+    reader: R,
+    // Decoded chars waiting to be lexed
+    chars: VecDeque<char>,
+    // Bytes read but not decoded yet, a UTF-8 char split by a refill
+    pending: Vec<u8>,
+    // Byte offset, line and column of the first char in `chars`
+    offset: usize,
+    line: usize,
+    col: usize,
+    eof: bool
+}
+
+impl<'a, R: BufRead> Tokens<'a, R> {
+    /// Read the next chunk of the input into `chars`, marking the end of it
+    fn refill(&mut self) -> io::Result<()> {
+        let read = {
+            let bytes = self.reader.fill_buf()?;
+            self.pending.extend_from_slice(bytes);
+
+            bytes.len()
+        };
+
+        self.reader.consume(read);
+
+        if read == 0 {
+            self.eof = true;
+
+            if !self.pending.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "input ends in the middle of a char"));
+            }
+        }
+
+        let valid = match str::from_utf8(&self.pending) {
+            Ok(s) => s.len(),
+            Err(ref e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e))
+        };
+
+        let decoded: Vec<u8> = self.pending.drain(..valid).collect();
+        self.chars.extend(str::from_utf8(&decoded).expect("Checked above").chars());
+
+        Ok(())
+    }
+
+    /// Move the position past `c`
+    fn advance(&mut self, c: char) {
+        self.offset += c.len_utf8();
+
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+    }
+
+    fn next_token(&mut self) -> io::Result<Option<Result<Token, LexError>>> {
+        while self.chars.is_empty() {
+            if self.eof { return Ok(None); }
+            self.refill()?;
+        }
+
+        let mut current = *self.dfa.initial();
+        let mut last_match = None;
+        let mut len = 0;
+
+        // The DFA may need more chars than buffered to know the longest match
+        loop {
+            if len == self.chars.len() {
+                if self.eof { break; }
+                self.refill()?;
+                continue;
+            }
+
+            match self.dfa.transition(current, &self.chars[len]) {
+                Some(next) if !self.dead.contains(&next) => current = next,
+                _ => break
+            }
+
+            len += 1;
+
+            if self.dfa.state_accept(current) {
+                last_match = Some((current, len));
+            }
+        }
+
+        match last_match {
+            Some((state, len)) => {
+                let lexeme: String = self.chars.drain(..len).collect();
+                let (offset, line, col) = (self.offset, self.line, self.col);
+
+                for c in lexeme.chars() {
+                    self.advance(c);
+                }
+
+                Ok(Some(Ok(Token { lexeme, offset, line, col, state })))
+            },
+            None => {
+                let unexpected = self.chars.pop_front().expect("Buffer is not empty");
+                let (offset, line, col) = (self.offset, self.line, self.col);
+
+                self.advance(unexpected);
+
+                Ok(Some(Err(LexError { offset, line, col, unexpected })))
+            }
+        }
+    }
+}
+
+impl<'a, R: BufRead> Iterator for Tokens<'a, R> {
+    type Item = io::Result<Result<Token, LexError>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_token() {
+            Ok(token) => token.map(Ok),
+            Err(err) => {
+                // Nothing more can be read after a failure
+                self.eof = true;
+                self.chars.clear();
+
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl Dfa<char> {
+    /// Same as `tokenize`, reading the input as needed instead of holding it all. A char that
+    /// starts no token is yielded as a `LexError` and lexing goes on after it. Failing to read
+    /// the input, or input that is not UTF-8, is yielded as the outer `io::Error` and ends it
+    #[allow(dead_code)]
+    pub fn tokenize_reader<'a, R: BufRead + 'a>(&'a self, reader: R) -> impl Iterator<Item = io::Result<Result<Token, LexError>>> + 'a {
+        Tokens {
+            dfa: self,
+            dead: self.get_dead_states().into_iter().collect(),
+            reader,
+            chars: VecDeque::new(),
+            pending: Vec::new(),
+            offset: 0,
+            line: 1,
+            col: 1,
+            eof: false
+        }
+    }
+}
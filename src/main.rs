@@ -1,201 +1,65 @@
-#![feature(vec_remove_item)]
 #[macro_use]
 extern crate log;
 extern crate env_logger;
 extern crate clap;
-
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+#[cfg(feature = "rand")]
+extern crate rand;
+
+mod builder;
 mod dfa;
+mod grammar;
+mod json;
+mod lexer;
+mod regex;
 
 use clap::{ App, Arg };
 use env_logger::LogBuilder;
 use dfa::Dfa;
-use std::path::PathBuf;
+use grammar::{ parse_grammar_into, INITIAL_STATE_NAME };
+use std::path::{ Path, PathBuf };
 use std::fs::{ File, OpenOptions };
-use std::io::{ BufRead, BufReader, BufWriter, Write };
-use std::env;
-use std::collections::HashMap;
-
-const INITIAL_STATE_CHAR: char = 'S';
-
-#[derive(PartialEq, Clone, Copy)]
-// enum Input: State Control for Token and Grammar recognizance
-// someword <- std token
-//
-// <S> ::= a<A> | b<B> | <>
-//  ^      ^       ^^^   ^^
-//  |      |       |||   ||
-//  |      |       |||   Epsilon
-//  |      |       Nonterminal Symbol (State)
-//  |      Terminal Symbol (Transition)
-//  State
-enum Input {
-    // Reading tokens as-is
-    // E.g.: if
-    // E.g.: else
-    Normal,
-    // Reading State definitions, like the left part of <S> ::= ...
-    StateDef,
-    // Reading the transitions, like the terminals of the right part of state definition
-    // E.g.: In `<S> ::= a<B> | b<E>`, the terminals are 'a' and 'b'
-    StateTransitions,
-    // Reading the transitions, like the nonterminals of the right part of state definition
-    // E.g.: In `<S> ::= e<C> | q<B> | <>`, the nonterminals are '<C>' '<B>' and '<>'.
-    // <> is aknowleged as Epsilon (Epsilon is a terminal symbol! But in this state it is aknowledged!)
-    // The bool member is to identify if any char exists inside "<>", eg: <B> = bool true and
-    // <> = false
-    StateTransitionTarget(bool)
-}
+use std::io::{ self, BufRead, BufReader, BufWriter, Write };
+use std::{ env, process };
 
-fn parse_grammar(files: &[&str]) -> Dfa<char> {
-    let mut reading = Input::Normal;
+fn parse_grammar(files: &[&str], start: &str) -> Dfa<char> {
     let mut dfa = Dfa::new();
-    let mut reader: BufReader<File>;
 
     for f in files {
-        // TODO: Translate to English (or maybe Esperanto!)
-        let file = File::open(f).expect("Não consegui ler os arquivos");
-        let mut temp_transition: Option<char> = None;
-        let mut grammar_mapper: HashMap<char, usize> = HashMap::new();
-
         debug!("Reading `{}`...", f);
-        reader = BufReader::new(file);
-
-        for l in reader.lines() {
-            // TODO: Fix non-helpful error message
-            // TODO: Translate to English (or maybe Esperanto!)
-            let line = l.expect("Houve um erro ao ler um arquivo");
-            debug!("Line: `{}`", line);
-
-            for c in line.chars() {
-                match reading {
-                    Input::Normal if c != ' ' => {
-                        if c == '<' {
-                            reading = Input::StateDef;
-                        } else {
-                            let state_index = dfa.add_state(false);
-                            dfa.create_transition_and_walk(c, state_index);
-                        }
-                    },
-                    Input::StateDef if c != ' ' => {
-                        match c {
-                            '<' => continue,
-                            '>' => reading = Input::StateTransitions,
-                            _   => {
-                                // Add to mapper which index solves to current State, e.g. <A> maps to
-                                // index 3, <E> to index 8...
-                                let index = if c == INITIAL_STATE_CHAR {
-                                    *dfa.initial()
-                                } else {
-                                    grammar_mapper.entry(c).or_insert_with(|| {
-                                        let state = dfa.add_state(false);
-                                        debug!("[DEF] Indexing {} to {}", c, state);
-
-                                        state
-                                    });
-
-                                    grammar_mapper[&c]
-                                };
-
-                                // If current char is == INITIAL_STATE_CHAR, rewind to initial
-                                // else, go to new state
-                                if c == INITIAL_STATE_CHAR { dfa.rewind(); }
-                                else { dfa.set_current(index).expect("This should not happen!"); }
-                            }
-                        }
-                    },
-                    Input::StateTransitions => {
-                        match c {
-                            '<'       => reading = Input::StateTransitionTarget(false),
-                            // Epsilon Transitions, `b` in <A> ::= a<A> | b | c<C> or in
-                            // <B> ::= a<B> | b
-                            '|' | ' ' => {
-                                if let Some(t) = temp_transition.take() {
-                                    let empty_state = dfa.add_state(true);
-                                    warn!("Creating new empty-state to {}: {}", t, empty_state);
-                                    dfa.create_transition(t, empty_state);
-                                }
-                            },
-                            ':' | '=' => continue,
-                            ch if ch != ' ' => {
-                                if temp_transition.is_none() {
-                                    temp_transition = Some(ch);
-                                } else {
-                                    // If there is two transitions, the grammar is not regular
-                                    warn!(
-                                        "Nonregular grammar detected (a.k.a. reassignment to temp_transition! '{}' -> '{:?}')",
-                                        c, temp_transition
-                                    );
-                                }
-                            },
-                            _ => ()
-                        }
-                    },
-                    Input::StateTransitionTarget(had_state) if c != ' ' => {
-                        if c == '>' {
-                            reading = Input::StateTransitions;
-
-                            // Check if is Epsilon (aka <>)
-                            if temp_transition.is_none() && ! had_state {
-                                dfa.set_current_state_accept(true)
-                            }
-                        } else {
-                            // In recognization, get the entry value if state exists.
-                            // If state doesn't exists yet, we need to map it [`or_insert`] and hope that
-                            // it will be defined in the future :P
-                            let target = if c == INITIAL_STATE_CHAR {
-                                *dfa.initial()
-                            } else {
-                                grammar_mapper.entry(c).or_insert_with(|| {
-                                    let state = dfa.add_state(false);
-                                    debug!("[TRANS] Indexing {} to {}", c, state);
-
-                                    state
-                                });
-
-                                grammar_mapper[&c]
-                            };
-
-                            if let Some(t) = temp_transition.take() {
-                                dfa.create_transition(t, target)
-                            } else {
-                                warn!("Epsilon-transition to <{}>", c);
-                            }
-
-                            reading = Input::StateTransitionTarget(true);
-                        }
-                    }
-                    _ => ()
-                }
-            }
-
-            // Line ends like: <A> ::= a<A> | b<B> | c
-            // and so 'c' is not parsed
-            if let Some(t) = temp_transition.take() {
-                let empty_state = dfa.add_state(true);
-                warn!("Creating new empty-state to {}: {}", t, empty_state);
-                dfa.create_transition(t, empty_state);
-            }
-
-            if reading == Input::Normal {
-                // We had finished the current line, so the last state accept the current token
-                dfa.set_current_state_accept(true);
-                dfa.rewind();
-            } else {
-                // Finished reading a line of grammar, must reset the state to keep reading
-                reading = Input::StateDef;
-            }
+
+        // `-` is the standard input, so grammars can be piped in
+        let parsed = if *f == "-" {
+            let stdin = io::stdin();
+
+            parse_grammar_into(&mut dfa, stdin.lock(), start)
+        } else {
+            let file = File::open(f).unwrap_or_else(|e| {
+                eprintln!("Could not open `{}`: {}", f, e);
+                process::exit(1);
+            });
+
+            parse_grammar_into(&mut dfa, BufReader::new(file), start)
+        };
+
+        if let Err(e) = parsed {
+            eprintln!("Could not parse `{}`: {}", f, e);
+            process::exit(1);
         }
     }
 
     dfa
 }
 
-fn dump_automata(aut: &Dfa<char>, p: &PathBuf) {
+fn dump_automata(aut: &Dfa<char>, p: &Path) {
     let mut fp: File;
     let mut writer: BufWriter<File>;
 
     {
-        let mut path = p.clone();
+        let mut path = p.to_path_buf();
         path.set_extension("dot");
         let dotfile = path.as_path();
 
@@ -211,7 +75,7 @@ fn dump_automata(aut: &Dfa<char>, p: &PathBuf) {
     }
 
     {
-        let mut path = p.clone();
+        let mut path = p.to_path_buf();
         path.set_extension("csv");
         let csvfile = path.as_path();
 
@@ -227,13 +91,35 @@ fn dump_automata(aut: &Dfa<char>, p: &PathBuf) {
     }
 }
 
+/// Print `ACCEPT` or `REJECT` for each line of stdin, exits with 1 if any was rejected
+fn simulate(dfa: &Dfa<char>) -> ! {
+    let stdin = io::stdin();
+    let mut rejected = false;
+
+    for line in stdin.lock().lines() {
+        let line = line.unwrap_or_else(|e| {
+            eprintln!("Could not read stdin: {}", e);
+            process::exit(1);
+        });
+
+        if dfa.accepts_str(&line) {
+            println!("ACCEPT");
+        } else {
+            println!("REJECT");
+            rejected = true;
+        }
+    }
+
+    process::exit(if rejected { 1 } else { 0 });
+}
+
 fn main() {
     let app = App::new("DFA Generator")
         .version("0.1.0")
         .author("Gabriel Henrique Rudey <gabriel.rudey@gmail.com>")
         .about("Create DFAs by Formal Grammars")
         .arg(Arg::with_name("files")
-             .help("The files to be parsed")
+             .help("The files to be parsed, `-` reads from stdin")
              .takes_value(true)
              .value_name("FILE")
              .multiple(true)
@@ -244,6 +130,24 @@ fn main() {
              .takes_value(true)
              .value_name("DIRECTORY")
              .help("The directory to dump debug files"))
+        .arg(Arg::with_name("format")
+             .short("f")
+             .long("format")
+             .takes_value(true)
+             .value_name("FORMAT")
+             .possible_values(&["csv", "dot", "json", "mermaid"])
+             .default_value("csv")
+             .help("The format of the resulting automaton"))
+        .arg(Arg::with_name("start")
+             .short("s")
+             .long("start")
+             .takes_value(true)
+             .value_name("NAME")
+             .default_value(INITIAL_STATE_NAME)
+             .help("The initial nonterminal of the grammars, without `<` and `>`"))
+        .arg(Arg::with_name("simulate")
+             .long("simulate")
+             .help("Run each line of stdin through the automaton instead of printing it"))
         .arg(Arg::with_name("verbosity")
              .short("v")
              .help("Set the log level")
@@ -266,11 +170,22 @@ fn main() {
 
     let files: Vec<&str>   = matches.values_of("files").unwrap().collect();
     let dump: Option<&str> = matches.value_of("dump");
+    let format: &str       = matches.value_of("format").unwrap();
+    let start: &str        = matches.value_of("start").unwrap();
+
+    if matches.is_present("simulate") && files.contains(&"-") {
+        eprintln!("Cannot read both the grammar and the strings to simulate from stdin");
+        process::exit(1);
+    }
 
-    let mut dfa = parse_grammar(files.as_slice());
+    let mut dfa = parse_grammar(files.as_slice(), start);
 
     info!("All files were parsed");
 
+    for (state, by, dests) in dfa.nondeterminism_report() {
+        debug!("State {} is nondeterministic by `{}`, going to {:?}", state, by, dests);
+    }
+
     // Debug or simply calculate the result
     if let Some(dir) = dump {
         let mut file = PathBuf::from(dir.to_owned());
@@ -299,5 +214,16 @@ fn main() {
         dfa.insert_error_state();
     }
 
-    println!("{}", dfa.to_csv());
+    if matches.is_present("simulate") {
+        simulate(&dfa);
+    }
+
+    let output = match format {
+        "dot"     => dfa.to_dot(),
+        "json"    => dfa.to_json(),
+        "mermaid" => dfa.to_mermaid(),
+        _         => dfa.to_csv()
+    };
+
+    println!("{}", output);
 }
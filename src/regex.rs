@@ -0,0 +1,381 @@
+use dfa::Dfa;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Debug, PartialEq, Clone)]
+// Regular expression tree, built through `alt`, `cat` and `star` to keep it simple
+enum Regex {
+    // Matches nothing (∅)
+    Empty,
+    // Matches only the empty string (ε)
+    Epsilon,
+    Symbol(char),
+    Concat(Box<Regex>, Box<Regex>),
+    Union(Box<Regex>, Box<Regex>),
+    Star(Box<Regex>)
+}
+
+fn alt(a: Regex, b: Regex) -> Regex {
+    match (a, b) {
+        (Regex::Empty, x) | (x, Regex::Empty) => x,
+        (ref x, ref y) if x == y => x.clone(),
+        (x, y) => Regex::Union(Box::new(x), Box::new(y))
+    }
+}
+
+fn cat(a: Regex, b: Regex) -> Regex {
+    match (a, b) {
+        (Regex::Empty, _) | (_, Regex::Empty) => Regex::Empty,
+        (Regex::Epsilon, x) | (x, Regex::Epsilon) => x,
+        (x, y) => Regex::Concat(Box::new(x), Box::new(y))
+    }
+}
+
+fn star(a: Regex) -> Regex {
+    match a {
+        Regex::Empty | Regex::Epsilon => Regex::Epsilon,
+        Regex::Star(x) => Regex::Star(x),
+        x => Regex::Star(Box::new(x))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RegexError {
+    /// A char that cannot start or continue an expression there, like `*` with nothing to
+    /// repeat or an unmatched `)`
+    UnexpectedChar { pos: usize, found: char },
+    /// A `(` never closed
+    UnclosedGroup { pos: usize },
+    /// A `\` at the end of the pattern
+    TrailingEscape
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RegexError::UnexpectedChar { pos, found } => write!(f, "unexpected `{}` at char {}", found, pos),
+            RegexError::UnclosedGroup { pos } => write!(f, "group opened at char {} is never closed", pos),
+            RegexError::TrailingEscape => write!(f, "pattern ends with an escape")
+        }
+    }
+}
+
+impl Error for RegexError {}
+
+/// Chars with a meaning in the regex syntax are escaped with `\`
+fn escape(c: char) -> String {
+    match c {
+        '|' | '*' | '+' | '?' | '(' | ')' | '\\' | '∅' => format!("\\{}", c),
+        c => c.to_string()
+    }
+}
+
+/// Render `re` to be placed where `prec` binds: 0 in unions, 1 in concatenations and 2 under
+/// a postfix operator. Parenthesis are only added when needed
+fn render(re: &Regex, prec: u8) -> String {
+    let (s, own) = match *re {
+        Regex::Empty => ("∅".to_string(), 2),
+        Regex::Epsilon => ("()".to_string(), 2),
+        Regex::Symbol(c) => (escape(c), 2),
+        // `x|ε` is written as `x?`
+        Regex::Union(ref a, ref b) if **a == Regex::Epsilon => (format!("{}?", render(b, 2)), 2),
+        Regex::Union(ref a, ref b) if **b == Regex::Epsilon => (format!("{}?", render(a, 2)), 2),
+        Regex::Union(ref a, ref b) => (format!("{}|{}", render(a, 0), render(b, 0)), 0),
+        Regex::Concat(ref a, ref b) => (format!("{}{}", render(a, 1), render(b, 1)), 1),
+        Regex::Star(ref a) => (format!("{}*", render(a, 2)), 2)
+    };
+
+    if own < prec { format!("({})", s) } else { s }
+}
+
+impl Dfa<char> {
+    /// Regular expression of the language accepted, through state elimination: a new start
+    /// and a new final state are linked by epsilon, then every original state is removed
+    /// moving its paths into the edges around it. Uses `|`, `*`, `?`, parenthesis and `\` to
+    /// escape, `()` for the empty string and `∅` if nothing is accepted
+    #[allow(dead_code)]
+    pub fn to_regex(&self) -> String {
+        let states: Vec<usize> = self.states().keys().cloned().collect();
+        let start = states.last().map_or(0, |s| s + 1);
+        let end = start + 1;
+        // Sorted, so the same DFA always gives the same expression
+        let mut edges: BTreeMap<(usize, usize), Regex> = BTreeMap::new();
+
+        {
+            let mut add = |from: usize, to: usize, re: Regex| {
+                let current = edges.remove(&(from, to)).unwrap_or(Regex::Empty);
+                edges.insert((from, to), alt(current, re));
+            };
+
+            add(start, *self.initial(), Regex::Epsilon);
+
+            for s in &states {
+                if self.state_accept(*s) {
+                    add(*s, end, Regex::Epsilon);
+                }
+            }
+
+            for (from, ts) in self.transitions() {
+                for t in ts {
+                    add(*from, t.dest(), Regex::Symbol(*t.by()));
+                }
+            }
+
+            for (from, es) in self.epsilon_transitions() {
+                for to in es {
+                    add(*from, *to, Regex::Epsilon);
+                }
+            }
+        }
+
+        for q in states {
+            let looping = star(edges.remove(&(q, q)).unwrap_or(Regex::Empty));
+            let incoming: Vec<(usize, Regex)> = edges.iter()
+                .filter(|&(&(_, to), _)| to == q)
+                .map(|(&(from, _), re)| (from, re.clone()))
+                .collect();
+            let outgoing: Vec<(usize, Regex)> = edges.iter()
+                .filter(|&(&(from, _), _)| from == q)
+                .map(|(&(_, to), re)| (to, re.clone()))
+                .collect();
+            let touching: Vec<(usize, usize)> = edges.keys()
+                .filter(|&&(from, to)| from == q || to == q)
+                .cloned()
+                .collect();
+
+            for edge in touching {
+                edges.remove(&edge);
+            }
+
+            for &(p, ref into) in &incoming {
+                for &(r, ref out) in &outgoing {
+                    let through = cat(cat(into.clone(), looping.clone()), out.clone());
+                    let current = edges.remove(&(p, r)).unwrap_or(Regex::Empty);
+
+                    edges.insert((p, r), alt(current, through));
+                }
+            }
+        }
+
+        render(&edges.remove(&(start, end)).unwrap_or(Regex::Empty), 0)
+    }
+}
+
+// Recursive descent over: alternation := concat ('|' concat)*, concat := postfix*,
+// postfix := atom ('*' | '+' | '?')* and atom := '(' alternation ')' | '∅' | '\\' char | char
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    pos: usize
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+
+        if c.is_some() { self.pos += 1; }
+
+        c
+    }
+
+    fn alternation(&mut self) -> Result<Regex, RegexError> {
+        let mut re = self.concat()?;
+
+        while self.chars.peek() == Some(&'|') {
+            self.next();
+            re = Regex::Union(Box::new(re), Box::new(self.concat()?));
+        }
+
+        Ok(re)
+    }
+
+    fn concat(&mut self) -> Result<Regex, RegexError> {
+        let mut re = Regex::Epsilon;
+
+        loop {
+            match self.chars.peek() {
+                None | Some(&'|') | Some(&')') => return Ok(re),
+                _ => re = cat(re, self.postfix()?)
+            }
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Regex, RegexError> {
+        let mut re = self.atom()?;
+
+        loop {
+            re = match self.chars.peek() {
+                Some(&'*') => star(re),
+                Some(&'+') => cat(re.clone(), star(re)),
+                Some(&'?') => alt(Regex::Epsilon, re),
+                _ => return Ok(re)
+            };
+
+            self.next();
+        }
+    }
+
+    fn atom(&mut self) -> Result<Regex, RegexError> {
+        let pos = self.pos;
+
+        match self.next() {
+            Some('(') => {
+                let re = self.alternation()?;
+
+                match self.next() {
+                    Some(')') => Ok(re),
+                    _ => Err(RegexError::UnclosedGroup { pos })
+                }
+            },
+            Some('∅') => Ok(Regex::Empty),
+            Some('\\') => self.next().map(Regex::Symbol).ok_or(RegexError::TrailingEscape),
+            Some(c) if c == '*' || c == '+' || c == '?' || c == ')' || c == '|' => {
+                Err(RegexError::UnexpectedChar { pos, found: c })
+            },
+            Some(c) => Ok(Regex::Symbol(c)),
+            // `concat` never asks for an atom at the end
+            None => unreachable!()
+        }
+    }
+}
+
+/// Thompson construction of `re` into `dfa`, returning its entry and exit states
+fn thompson(dfa: &mut Dfa<char>, re: &Regex) -> (usize, usize) {
+    match *re {
+        Regex::Empty => (dfa.add_state(false), dfa.add_state(false)),
+        Regex::Epsilon => {
+            let state = dfa.add_state(false);
+
+            (state, state)
+        },
+        Regex::Symbol(c) => {
+            let (entry, exit) = (dfa.add_state(false), dfa.add_state(false));
+
+            dfa.create_transition_between(&entry, &exit, c);
+
+            (entry, exit)
+        },
+        Regex::Concat(ref a, ref b) => {
+            let (entry, middle) = thompson(dfa, a);
+            let (next, exit) = thompson(dfa, b);
+
+            dfa.create_epsilon_transition_between(&middle, &next);
+
+            (entry, exit)
+        },
+        Regex::Union(ref a, ref b) => {
+            let entry = dfa.add_state(false);
+            let (a_entry, a_exit) = thompson(dfa, a);
+            let (b_entry, b_exit) = thompson(dfa, b);
+            let exit = dfa.add_state(false);
+
+            dfa.create_epsilon_transition_between(&entry, &a_entry);
+            dfa.create_epsilon_transition_between(&entry, &b_entry);
+            dfa.create_epsilon_transition_between(&a_exit, &exit);
+            dfa.create_epsilon_transition_between(&b_exit, &exit);
+
+            (entry, exit)
+        },
+        Regex::Star(ref a) => {
+            let entry = dfa.add_state(false);
+            let (inner_entry, inner_exit) = thompson(dfa, a);
+
+            dfa.create_epsilon_transition_between(&entry, &inner_entry);
+            dfa.create_epsilon_transition_between(&inner_exit, &entry);
+
+            (entry, entry)
+        }
+    }
+}
+
+/// Build a DFA accepting the language of `pattern`, which may use concatenation, `|`, `*`,
+/// `+`, `?`, parenthesis, `∅` for nothing and `\` to escape any of them, so `to_regex`
+/// output is read back to the same language. An epsilon-NFA is made by Thompson
+/// construction and then determinized
+#[allow(dead_code)]
+pub fn from_regex(pattern: &str) -> Result<Dfa<char>, RegexError> {
+    let mut parser = Parser { chars: pattern.chars().peekable(), pos: 0 };
+    let re = parser.alternation()?;
+
+    // Only a `)` can stop the top-level alternation before the end
+    if let Some(found) = parser.next() {
+        return Err(RegexError::UnexpectedChar { pos: parser.pos - 1, found });
+    }
+
+    let mut dfa = Dfa::new();
+    let (entry, exit) = thompson(&mut dfa, &re);
+
+    dfa.create_epsilon_transition_between(&0, &entry);
+    dfa.set_current(exit).expect("Thompson exit state exists");
+    dfa.set_current_state_accept(true);
+    dfa.rewind();
+
+    dfa.determinize();
+    dfa.remove_unreachable_states();
+
+    Ok(dfa)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_regex_of_ab_star() {
+        let mut dfa = Dfa::new();
+        let b = dfa.add_state(false);
+
+        dfa.set_current_state_accept(true);
+        dfa.create_transition_between(&0, &b, 'a');
+        dfa.create_transition_between(&b, &0, 'b');
+
+        assert_eq!(dfa.to_regex(), "(a(ba)*b)?");
+    }
+
+    #[test]
+    fn empty_language_round_trips() {
+        let empty: Dfa<char> = Dfa::new();
+        let pattern = empty.to_regex();
+
+        assert_eq!(pattern, "∅");
+        assert!(from_regex(&pattern).unwrap().is_empty());
+    }
+
+    #[test]
+    fn escaped_empty_set_is_a_symbol() {
+        let dfa = from_regex("\\∅").unwrap();
+
+        assert!(dfa.accepts_str("∅"));
+        assert!(from_regex(&dfa.to_regex()).unwrap().equivalent(&dfa));
+    }
+
+    #[test]
+    fn star_of_alternation() {
+        let dfa = from_regex("a(b|c)*").unwrap();
+
+        assert!(dfa.accepts_str("abbc"));
+        assert!(dfa.accepts_str("a"));
+        assert!(!dfa.accepts_str("ad"));
+        assert!(!dfa.accepts_str("bc"));
+        assert!(dfa.is_deterministic());
+    }
+
+    #[test]
+    fn to_regex_round_trips() {
+        for pattern in &["(ab)*", "a|b*c", "a?b+", "()", "a(b|∅)", "\\*x|\\|"] {
+            let dfa = from_regex(pattern).unwrap();
+            let back = from_regex(&dfa.to_regex()).unwrap();
+
+            assert!(back.equivalent(&dfa), "{} gave {}", pattern, dfa.to_regex());
+        }
+    }
+
+    #[test]
+    fn malformed_patterns() {
+        for pattern in &["(ab", "a)", "*a", "a\\"] {
+            assert!(from_regex(pattern).is_err(), "{}", pattern);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process::{ Command, Output, Stdio };
+
+/// Run the binary with `args`, writing `stdin` to it
+fn lexan(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lexan"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Could not run lexan");
+
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn dot_format() {
+    let output = lexan(&["-f", "dot", "tests/grammar.in"], "");
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().starts_with("digraph"));
+}
+
+#[test]
+fn simulate_strings_from_stdin() {
+    let output = lexan(&["--simulate", "tests/quoted.in"], "ifaa\nelsa\n");
+
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "ACCEPT\nREJECT\n");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn grammar_from_stdin() {
+    let output = lexan(&["-"], "<S> ::= a<S> | b\n");
+    let csv = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(csv, "State,a,b\n-><0>,<0>,<1>\n*<1>,<2>,<2>\n<2>,<2>,<2>\n\n");
+}
+
+#[test]
+fn dump_shows_nondeterministic_fan_out() {
+    let dir = env::temp_dir().join(format!("lexan-dump-{}", std::process::id()));
+
+    fs::create_dir_all(&dir).unwrap();
+
+    let output = lexan(&["-d", dir.to_str().unwrap(), "tests/ndetgrammar.in"], "");
+    let dot = fs::read_to_string(dir.join("1fa.dot")).unwrap();
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    // Red edges are the nondeterministic ones, `<S>` goes to two states by `a`
+    let fan_out: Vec<&str> = dot.lines().filter(|l| l.starts_with("0 -> ") && l.ends_with("[label=\"a\", color=red];")).collect();
+
+    assert!(output.status.success());
+    assert_eq!(fan_out.len(), 2);
+}